@@ -0,0 +1,152 @@
+//! Ocean floor classification.
+//!
+//! Depth below sea level splits the ocean into the shallow shelf around the
+//! coasts, the slope dropping off it and the deep abyssal plains.  Trenches
+//! are the narrow gashes in the deep floor, where it falls well below the
+//! floor around it (as it does where tectonic plates subduct).
+
+use crate::climate;
+use crate::terrain::DynGrid;
+use serde::{Deserialize, Serialize};
+
+/// Ocean floor classification thresholds, depths in height map units below
+/// sea level and distances in tiles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bathymetry {
+    /// Deepest water still on the continental shelf.
+    pub shelf_depth: f64,
+    /// Distance from land beyond which even shallow water isn't shelf.
+    pub shelf_width: f64,
+    /// Depth where the slope levels out onto the abyssal plain.
+    pub abyss_depth: f64,
+    /// How much deeper than the mean of the floor around it a tile below
+    /// `abyss_depth` has to be to count as a trench, as a fraction of that
+    /// mean.
+    pub trench_relief: f64,
+}
+
+impl Default for Bathymetry {
+    fn default() -> Bathymetry {
+        Bathymetry {
+            shelf_depth: 0.08,
+            shelf_width: 6.0,
+            abyss_depth: 0.2,
+            trench_relief: 0.2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Seafloor {
+    /// Shallow water near the coast: the richest fishing and the only
+    /// anchorages, but shoals for deep hulls.
+    Shelf,
+    /// Where the shelf drops away into deep water.
+    Slope,
+    /// The flat floor of the deep ocean, open water for any ship.
+    AbyssalPlain,
+    /// The deepest water, in narrow gashes through the abyssal plain.
+    Trench,
+}
+
+impl Seafloor {
+    /// Display colour used by both the terminal and image renderers.
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            Seafloor::Shelf => [70, 160, 200],
+            Seafloor::Slope => [30, 90, 170],
+            Seafloor::AbyssalPlain => [10, 40, 120],
+            Seafloor::Trench => [20, 0, 60],
+        }
+    }
+
+    /// Character drawn for the floor on terminal maps.
+    pub fn tile_char(&self) -> char {
+        match self {
+            Seafloor::Shelf => '.',
+            Seafloor::Slope => '~',
+            Seafloor::AbyssalPlain => '=',
+            Seafloor::Trench => 'v',
+        }
+    }
+}
+
+/// Tiles around each tile whose mean depth a trench is measured against.
+const TRENCH_RADIUS: isize = 3;
+
+/// Classify the floor under every `ocean` tile, `None` elsewhere.
+pub fn classify_seafloor(
+    height: &DynGrid<f64>,
+    ocean: &DynGrid<bool>,
+    sea_level: f64,
+    params: &Bathymetry,
+) -> DynGrid<Option<Seafloor>> {
+    let (width, depth) = (height.width(), height.height());
+
+    // Distance to land is the coast distance with land and sea swapped
+    let mut land = DynGrid::new(width, depth, 1.0);
+    for x in 0..width {
+        for y in 0..depth {
+            if !ocean[x][y] {
+                land[x][y] = 0.0;
+            }
+        }
+    }
+    let land_distance = climate::coast_distance(&land, 0.5);
+
+    let mut seafloor = DynGrid::new(width, depth, None);
+    for x in 0..width {
+        for y in 0..depth {
+            if !ocean[x][y] {
+                continue;
+            }
+            let below = sea_level - height[x][y];
+            seafloor[x][y] = Some(if below <= params.shelf_depth {
+                if land_distance[x][y] <= params.shelf_width {
+                    Seafloor::Shelf
+                } else {
+                    Seafloor::Slope
+                }
+            } else if below < params.abyss_depth {
+                Seafloor::Slope
+            } else if below / abyss_depth_around(height, ocean, sea_level, params, x, y)
+                >= 1.0 + params.trench_relief
+            {
+                Seafloor::Trench
+            } else {
+                Seafloor::AbyssalPlain
+            });
+        }
+    }
+    seafloor
+}
+
+/// Mean depth below `sea_level` of the abyssal floor within `TRENCH_RADIUS`
+/// of `(x, y)`, the tile itself included.  Shallower water nearby is left
+/// out, so the foot of the slope doesn't stand out from it as a trench.
+fn abyss_depth_around(
+    height: &DynGrid<f64>,
+    ocean: &DynGrid<bool>,
+    sea_level: f64,
+    params: &Bathymetry,
+    x: usize,
+    y: usize,
+) -> f64 {
+    let (mut total, mut count) = (0.0, 0);
+    for dx in -TRENCH_RADIUS..=TRENCH_RADIUS {
+        for dy in -TRENCH_RADIUS..=TRENCH_RADIUS {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            if nx < 0 || ny < 0 || nx >= height.width() as isize || ny >= height.height() as isize {
+                continue;
+            }
+            let (nx, ny) = (nx as usize, ny as usize);
+            let below = sea_level - height[nx][ny];
+            if ocean[nx][ny] && below >= params.abyss_depth {
+                total += below;
+                count += 1;
+            }
+        }
+    }
+    total / count as f64
+}
@@ -0,0 +1,269 @@
+//! Temperature/precipitation to biome classification, as data.
+//!
+//! A `BiomeTable` is an ordered list of rules, each covering a temperature
+//! band and a precipitation band whose limits can vary linearly with
+//! temperature, which is enough to trace out a Whittaker diagram.  The first
+//! rule matching a climate wins.
+//!
+//! `Landforms` then overrides the climate biome where the terrain matters
+//! more than the weather: mountains, coasts and river floodplains.
+
+use crate::terrain::Biome;
+use serde::{Deserialize, Serialize};
+
+/// Precipitation limit in cm/year: `base + per_degree * temperature`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrecipBound {
+    pub base: f64,
+    #[serde(default)]
+    pub per_degree: f64,
+}
+
+impl PrecipBound {
+    pub fn constant(cm: f64) -> PrecipBound {
+        PrecipBound {
+            base: cm,
+            per_degree: 0.0,
+        }
+    }
+
+    pub fn linear(base: f64, per_degree: f64) -> PrecipBound {
+        PrecipBound { base, per_degree }
+    }
+
+    pub fn at(&self, temp: f64) -> f64 {
+        self.base + self.per_degree * temp
+    }
+}
+
+/// Climate region assigned to one biome.  Lower limits are inclusive, upper
+/// limits exclusive, and a missing limit is unbounded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BiomeRule {
+    pub biome: Biome,
+    /// Degrees C
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_temp: Option<f64>,
+    /// Degrees C
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temp: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_precip: Option<PrecipBound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_precip: Option<PrecipBound>,
+}
+
+impl BiomeRule {
+    pub fn matches(&self, temp: f64, precip: f64) -> bool {
+        self.min_temp.is_none_or(|t| temp >= t)
+            && self.max_temp.is_none_or(|t| temp < t)
+            && self.min_precip.is_none_or(|p| precip >= p.at(temp))
+            && self.max_precip.is_none_or(|p| precip < p.at(temp))
+    }
+}
+
+/// Terrain thresholds for the biomes climate alone doesn't decide.  Heights
+/// are in height map units above sea level, or above 0 where the sea lies
+/// lower.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Landforms {
+    /// Land at least this far above sea level is Alpine.
+    pub alpine_height: f64,
+    /// Land at least this far above sea level, and below `alpine_height`,
+    /// is Montane.
+    pub montane_height: f64,
+    /// Coastal land less than this far above sea level is Beach or Mangrove.
+    pub beach_height: f64,
+    /// Degrees C
+    pub mangrove_min_temp: f64,
+    /// cm/year
+    pub mangrove_min_precip: f64,
+    /// cm/year a river or lake side needs to be Wetland.
+    pub wetland_min_precip: f64,
+    /// Degrees C a river or lake side needs to be Wetland rather than frozen.
+    pub wetland_min_temp: f64,
+    /// Largest height difference to a neighbor that still counts as flat
+    /// enough for Wetland.
+    pub wetland_max_relief: f64,
+}
+
+impl Default for Landforms {
+    fn default() -> Landforms {
+        Landforms {
+            alpine_height: 0.6,
+            montane_height: 0.4,
+            beach_height: 0.05,
+            mangrove_min_temp: 22.0,
+            mangrove_min_precip: 150.0,
+            wetland_min_precip: 100.0,
+            wetland_min_temp: 0.0,
+            wetland_max_relief: 0.05,
+        }
+    }
+}
+
+/// What the biome of a single land tile depends on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileConditions {
+    /// Degrees C
+    pub temp: f64,
+    /// cm/year
+    pub precip: f64,
+    /// Height above sea level, or above 0 where the sea lies lower.
+    pub elevation: f64,
+    /// Borders the ocean.
+    pub coastal: bool,
+    /// Is or borders a river or lake.
+    pub fresh_water: bool,
+    /// Largest height difference to a neighbor.
+    pub relief: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BiomeTable {
+    /// Biome for climates no rule matches.
+    pub fallback: Biome,
+    pub rules: Vec<BiomeRule>,
+    #[serde(default)]
+    pub landforms: Landforms,
+}
+
+impl BiomeTable {
+    /// The Whittaker diagram, see
+    /// https://en.wikipedia.org/wiki/Biome#/media/File:Climate_influence_on_terrestrial_biome.svg
+    pub fn whittaker() -> BiomeTable {
+        let rule = |biome, min_temp, max_temp, min_precip, max_precip| BiomeRule {
+            biome,
+            min_temp,
+            max_temp,
+            min_precip,
+            max_precip,
+        };
+
+        // Tropical seasonal forest/savanna 230 cm at 22 C, 280 cm at 32 C
+        let tropical_rain = PrecipBound::linear(170.0, 5.0);
+        // Subtropical desert 50 cm at 22 C, 100 cm at 32 C
+        let tropical_dry = PrecipBound::linear(-60.0, 5.0);
+        // Temperate seasonal forest 170 cm at 7 C, 230 at 22 C
+        let temperate_rain = PrecipBound::linear(142.0, 4.0);
+        // Woodland/Shrubland 50 cm at 7 C, 120 cm at 22 C
+        let temperate_dry = PrecipBound::linear(17.33, 4.67);
+
+        BiomeTable {
+            // Anything below 0.0 C is Tundra, roughly
+            fallback: Biome::Tundra,
+            rules: vec![
+                rule(Biome::IceSheet, None, Some(-5.0), None, None),
+                rule(
+                    Biome::TropicalRainforest,
+                    Some(22.0),
+                    None,
+                    Some(tropical_rain),
+                    None,
+                ),
+                rule(
+                    Biome::Savanna,
+                    Some(22.0),
+                    None,
+                    Some(tropical_dry),
+                    Some(tropical_rain),
+                ),
+                rule(
+                    Biome::SubtropicalDesert,
+                    Some(22.0),
+                    None,
+                    None,
+                    Some(tropical_dry),
+                ),
+                rule(
+                    Biome::TemperateRainforest,
+                    Some(7.0),
+                    Some(22.0),
+                    Some(temperate_rain),
+                    None,
+                ),
+                rule(
+                    Biome::TemperateSeasonalForest,
+                    Some(7.0),
+                    Some(22.0),
+                    Some(temperate_dry),
+                    Some(temperate_rain),
+                ),
+                rule(
+                    Biome::Shrubland,
+                    Some(7.0),
+                    Some(22.0),
+                    Some(PrecipBound::constant(50.0)),
+                    Some(temperate_dry),
+                ),
+                // Temperate grassland
+                rule(
+                    Biome::Steppe,
+                    Some(0.0),
+                    Some(22.0),
+                    Some(PrecipBound::constant(25.0)),
+                    Some(PrecipBound::constant(50.0)),
+                ),
+                rule(
+                    Biome::ColdDesert,
+                    Some(0.0),
+                    Some(22.0),
+                    None,
+                    Some(PrecipBound::constant(25.0)),
+                ),
+                rule(
+                    Biome::BorealForest,
+                    Some(0.0),
+                    Some(7.0),
+                    Some(PrecipBound::constant(40.0)),
+                    None,
+                ),
+            ],
+            landforms: Landforms::default(),
+        }
+    }
+
+    /// Biome for a temperature in degrees C and precipitation in cm/year.
+    pub fn classify(&self, temp: f64, precip: f64) -> Biome {
+        self.rules
+            .iter()
+            .find(|r| r.matches(temp, precip))
+            .map_or(self.fallback, |r| r.biome)
+    }
+
+    /// Biome for a land tile: the climate biome unless ice, mountains, a
+    /// low coast or a flat waterside takes precedence, in that order.
+    pub fn classify_tile(&self, tile: &TileConditions) -> Biome {
+        let climate = self.classify(tile.temp, tile.precip);
+        let land = &self.landforms;
+
+        if climate == Biome::IceSheet {
+            climate
+        } else if tile.elevation >= land.alpine_height {
+            Biome::Alpine
+        } else if tile.elevation >= land.montane_height {
+            Biome::Montane
+        } else if tile.coastal && tile.elevation < land.beach_height {
+            if tile.temp >= land.mangrove_min_temp && tile.precip >= land.mangrove_min_precip {
+                Biome::Mangrove
+            } else {
+                Biome::Beach
+            }
+        } else if tile.fresh_water
+            && tile.precip >= land.wetland_min_precip
+            && tile.relief <= land.wetland_max_relief
+            && tile.temp >= land.wetland_min_temp
+        {
+            Biome::Wetland
+        } else {
+            climate
+        }
+    }
+}
+
+impl Default for BiomeTable {
+    fn default() -> BiomeTable {
+        BiomeTable::whittaker()
+    }
+}
@@ -0,0 +1,498 @@
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rustpcg::render_image;
+use rustpcg::{ConfigError, DynGrid, Feature, HeightmapFile, Landmass, Preset, WorldConfig};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(version, about = "Procedural map generator")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate a world and save its fully resolved config (including seed)
+    Generate {
+        #[command(flatten)]
+        world: WorldArgs,
+        /// Where to write the resolved world config (.toml or .json)
+        #[arg(short, long, default_value = "world.out.toml")]
+        output: PathBuf,
+        /// Overwrite the output file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a world and show one of its layers in the terminal
+    #[command(alias = "render")]
+    View {
+        #[command(flatten)]
+        world: WorldArgs,
+        #[arg(short, long, value_enum, default_value_t = ViewLayer::Terrain)]
+        layer: ViewLayer,
+    },
+    /// Generate a world and write layers to files
+    Export {
+        #[command(flatten)]
+        world: WorldArgs,
+        /// Layers to export, may be repeated
+        #[arg(short, long = "layer", value_enum, default_values_t = [ExportLayer::Height])]
+        layers: Vec<ExportLayer>,
+        /// Output formats, may be repeated
+        #[arg(short, long = "format", value_enum, default_values_t = [ExportFormat::Png])]
+        formats: Vec<ExportFormat>,
+        /// Output path prefix, files are named <OUTPUT>_<layer>.<format>
+        #[arg(short, long, default_value = "output")]
+        output: PathBuf,
+    },
+}
+
+/// Options shared by every subcommand describing which world to build.
+#[derive(Args)]
+pub struct WorldArgs {
+    /// World config file (.toml or .json)
+    #[arg(short, long, conflicts_with = "preset")]
+    config: Option<PathBuf>,
+    /// Named generation preset
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(Preset::ALL.map(|p| p.name()))
+            .map(|s| s.parse::<Preset>().unwrap()),
+    )]
+    preset: Option<Preset>,
+    /// Map size as WIDTHxHEIGHT, overrides the config file
+    #[arg(long, value_parser = parse_size)]
+    size: Option<(usize, usize)>,
+    /// World seed, overrides the config file
+    #[arg(short, long)]
+    seed: Option<u32>,
+    /// Height map file (.png or .asc) to use instead of generating one
+    #[arg(long)]
+    heightmap: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ViewLayer {
+    Terrain,
+    Biome,
+    Precipitation,
+    Temperature,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportLayer {
+    Height,
+    Biome,
+    Precipitation,
+    Temperature,
+    Watershed,
+    Sediment,
+    Plate,
+    CoastDistance,
+    Currents,
+    Koppen,
+    Seafloor,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Png,
+    Csv,
+    Json,
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+    let w = w.trim().parse().map_err(|e| format!("bad width: {}", e))?;
+    let h = h.trim().parse().map_err(|e| format!("bad height: {}", e))?;
+    Ok((w, h))
+}
+
+impl WorldArgs {
+    /// Resolve preset, config file and overrides into a validated config.
+    pub fn world_config(&self) -> Result<WorldConfig, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => WorldConfig::load(path)?,
+            None => WorldConfig::default(),
+        };
+        if let Some(preset) = self.preset {
+            config.autogen = preset.config();
+        }
+        if let Some((width, height)) = self.size {
+            config.width = width;
+            config.height = height;
+        }
+        if self.seed.is_some() {
+            config.autogen.seed = self.seed;
+        }
+        if let Some(path) = &self.heightmap {
+            config.autogen.heightmap = Some(HeightmapFile::new(path));
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn generate(&self) -> Result<(WorldConfig, Landmass), ConfigError> {
+        let config = self.world_config()?;
+        let mut land = Landmass::new(config.width, config.height);
+        land.autogen(&config.autogen);
+        Ok((config, land))
+    }
+}
+
+pub fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Generate {
+            world,
+            output,
+            force,
+        } => {
+            if output.exists() && !force {
+                return Err(format!(
+                    "{} already exists, pass --force to overwrite it",
+                    output.display()
+                )
+                .into());
+            }
+            let (mut config, land) = world.generate()?;
+            config.autogen.seed = Some(land.seed);
+            config.save(&output)?;
+
+            let ocean = land
+                .features
+                .values()
+                .filter(|f| **f == Feature::Ocean)
+                .count();
+            let river = land
+                .features
+                .values()
+                .filter(|f| **f == Feature::River)
+                .count();
+            let tiles = config.width * config.height;
+            println!(
+                "Generated {}x{} world with seed {}: {:.1}% land, {} river tiles, {} lakes",
+                config.width,
+                config.height,
+                land.seed,
+                100.0 * (tiles - ocean) as f64 / tiles as f64,
+                river,
+                land.lakes.len(),
+            );
+            println!("Wrote {}", output.display());
+        }
+        Command::View { world, layer } => {
+            let (_, land) = world.generate()?;
+            match layer {
+                ViewLayer::Terrain => land.tui_render(),
+                ViewLayer::Biome => land.biome_tui_render(),
+                ViewLayer::Precipitation => land.precip_tui_render(),
+                ViewLayer::Temperature => land.temperature_tui_render(),
+            }
+        }
+        Command::Export {
+            world,
+            layers,
+            formats,
+            output,
+        } => {
+            let (_, land) = world.generate()?;
+            for layer in &layers {
+                for format in &formats {
+                    let path = export_path(&output, *layer, *format);
+                    export(&land, *layer, *format, &path)?;
+                    println!("Wrote {}", path.display());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn export_path(output: &Path, layer: ExportLayer, format: ExportFormat) -> PathBuf {
+    let layer = layer.to_possible_value().unwrap();
+    let format = format.to_possible_value().unwrap();
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("_{}.{}", layer.get_name(), format.get_name()));
+    output.with_file_name(name)
+}
+
+/// Map a [-1, 1] grid onto greyscale pixel values.
+fn greyscale(grid: &DynGrid<f64>) -> Vec<Vec<u8>> {
+    grid.to_vecs()
+        .iter()
+        .map(|row| row.iter().map(|v| ((127.0 * v) + 128.0) as u8).collect())
+        .collect()
+}
+
+/// Stretch a grid in real units (°C, cm) onto greyscale, its lowest value
+/// as black and highest as white.
+fn greyscale_range(grid: &DynGrid<f64>) -> Vec<Vec<u8>> {
+    let values = grid.to_vecs();
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(*v), hi.max(*v))
+        });
+    let scale = if max > min { 255.0 / (max - min) } else { 0.0 };
+    values
+        .iter()
+        .map(|row| row.iter().map(|v| ((v - min) * scale) as u8).collect())
+        .collect()
+}
+
+/// Map a signed grid onto red above 0 and blue below, full strength at its
+/// largest magnitude.
+fn warm_cold(grid: &DynGrid<f64>) -> Vec<Vec<[u8; 3]>> {
+    let values = grid.to_vecs();
+    let max = values
+        .iter()
+        .flatten()
+        .fold(0.0, |m: f64, v| m.max(v.abs()));
+    let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+    values
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| {
+                    let level = (v.abs() * scale) as u8;
+                    if *v > 0.0 {
+                        [level, 0, 0]
+                    } else {
+                        [0, 0, level]
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Map a non-negative grid onto greyscale, its largest finite value and
+/// anything infinite as white.
+fn greyscale_depth(grid: &DynGrid<f64>) -> Vec<Vec<u8>> {
+    let max = grid
+        .to_vecs()
+        .iter()
+        .flatten()
+        .filter(|v| v.is_finite())
+        .fold(0.0, |m: f64, v| m.max(*v));
+    let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+    grid.to_vecs()
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| {
+                    if v.is_finite() {
+                        (v * scale) as u8
+                    } else {
+                        255
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Arbitrary but stable colour per basin (or plate), black for none.
+fn basin_color(basin: Option<usize>) -> [u8; 3] {
+    match basin {
+        Some(b) => {
+            let h = (b as u32 + 1).wrapping_mul(0x9E37_79B1);
+            [(h >> 24) as u8, (h >> 16) as u8, (h >> 8) as u8]
+        }
+        None => [0, 0, 0],
+    }
+}
+
+/// Cell values of `layer` for the text formats, indexed [x][y].
+fn cell_values(land: &Landmass, layer: ExportLayer) -> Vec<Vec<Value>> {
+    match layer {
+        ExportLayer::Height => to_values(&land.height_map),
+        ExportLayer::Precipitation => to_values(&land.precip_map),
+        ExportLayer::Temperature => to_values(&land.temperature_map),
+        ExportLayer::Sediment => to_values(&land.sediment_map),
+        ExportLayer::CoastDistance => to_values(&land.coast_distance_map),
+        ExportLayer::Currents => to_values(&land.current_map),
+        ExportLayer::Plate => land
+            .plate_map
+            .to_vecs()
+            .iter()
+            .map(|row| row.iter().map(|p| Value::from(*p)).collect())
+            .collect(),
+        ExportLayer::Biome => land
+            .biome_map
+            .to_vecs()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|b| Value::from(format!("{:?}", b)))
+                    .collect()
+            })
+            .collect(),
+        ExportLayer::Koppen => land
+            .koppen_map
+            .to_vecs()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|k| match k {
+                        Some(k) => Value::from(format!("{:?}", k)),
+                        None => Value::Null,
+                    })
+                    .collect()
+            })
+            .collect(),
+        ExportLayer::Seafloor => land
+            .seafloor_map
+            .to_vecs()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|f| match f {
+                        Some(f) => Value::from(format!("{:?}", f)),
+                        None => Value::Null,
+                    })
+                    .collect()
+            })
+            .collect(),
+        ExportLayer::Watershed => land
+            .watersheds
+            .basin_map
+            .to_vecs()
+            .iter()
+            .map(|row| row.iter().map(|b| Value::from(*b)).collect())
+            .collect(),
+    }
+}
+
+fn export(
+    land: &Landmass,
+    layer: ExportLayer,
+    format: ExportFormat,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let filename = path.to_string_lossy();
+
+    match format {
+        ExportFormat::Png => match layer {
+            ExportLayer::Height => {
+                render_image::render_greyscale(&filename, &greyscale(&land.height_map))?
+            }
+            ExportLayer::Precipitation => {
+                render_image::render_greyscale(&filename, &greyscale_range(&land.precip_map))?
+            }
+            ExportLayer::Temperature => {
+                render_image::render_greyscale(&filename, &greyscale_range(&land.temperature_map))?
+            }
+            ExportLayer::Sediment => {
+                render_image::render_greyscale(&filename, &greyscale_depth(&land.sediment_map))?
+            }
+            ExportLayer::CoastDistance => render_image::render_greyscale(
+                &filename,
+                &greyscale_depth(&land.coast_distance_map),
+            )?,
+            ExportLayer::Currents => {
+                render_image::render_rgb(&filename, &warm_cold(&land.current_map))?
+            }
+            ExportLayer::Plate => {
+                let colors: Vec<Vec<[u8; 3]>> = land
+                    .plate_map
+                    .to_vecs()
+                    .iter()
+                    .map(|row| row.iter().map(|p| basin_color(*p)).collect())
+                    .collect();
+                render_image::render_rgb(&filename, &colors)?
+            }
+            ExportLayer::Biome => {
+                let colors: Vec<Vec<[u8; 3]>> = land
+                    .biome_map
+                    .to_vecs()
+                    .iter()
+                    .map(|row| row.iter().map(|b| b.rgb()).collect())
+                    .collect();
+                render_image::render_rgb(&filename, &colors)?
+            }
+            ExportLayer::Koppen => {
+                let colors: Vec<Vec<[u8; 3]>> = land
+                    .koppen_map
+                    .to_vecs()
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|k| k.map_or([0, 0, 0], |k| k.rgb()))
+                            .collect()
+                    })
+                    .collect();
+                render_image::render_rgb(&filename, &colors)?
+            }
+            ExportLayer::Seafloor => {
+                let colors: Vec<Vec<[u8; 3]>> = land
+                    .seafloor_map
+                    .to_vecs()
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|f| f.map_or([0, 0, 0], |f| f.rgb()))
+                            .collect()
+                    })
+                    .collect();
+                render_image::render_rgb(&filename, &colors)?
+            }
+            ExportLayer::Watershed => {
+                let colors: Vec<Vec<[u8; 3]>> = land
+                    .watersheds
+                    .basin_map
+                    .to_vecs()
+                    .iter()
+                    .map(|row| row.iter().map(|b| basin_color(*b)).collect())
+                    .collect();
+                render_image::render_rgb(&filename, &colors)?
+            }
+        },
+        ExportFormat::Csv => {
+            // One line per map row, so the file reads like the image
+            let cells = cell_values(land, layer);
+            let mut out = String::new();
+            for y in 0..land.height() {
+                let line: Vec<String> = cells
+                    .iter()
+                    .map(|col| match &col[y] {
+                        Value::Null => String::new(),
+                        value => value.to_string(),
+                    })
+                    .collect();
+                out.push_str(&line.join(","));
+                out.push('\n');
+            }
+            fs::write(path, out)?;
+        }
+        ExportFormat::Json => {
+            let cells = cell_values(land, layer);
+            let rows: Vec<Vec<&Value>> = (0..land.height())
+                .map(|y| cells.iter().map(|col| &col[y]).collect())
+                .collect();
+            let value = serde_json::json!({
+                "width": land.width(),
+                "height": land.height(),
+                "seed": land.seed,
+                "rows": rows,
+            });
+            fs::write(path, serde_json::to_string(&value)?)?;
+        }
+    }
+    Ok(())
+}
+
+fn to_values(grid: &DynGrid<f64>) -> Vec<Vec<Value>> {
+    grid.to_vecs()
+        .iter()
+        .map(|row| row.iter().map(|v| Value::from(*v)).collect())
+        .collect()
+}
@@ -0,0 +1,457 @@
+//! Climate models driven by the terrain.
+//!
+//! Moisture is carried by the prevailing winds of three cells per hemisphere:
+//! easterly trade winds up to 30 degrees, westerlies up to 60 and polar
+//! easterlies beyond.  Air picks moisture up over the ocean and carries it
+//! inland, raining a little of it everywhere and much more wherever it is
+//! forced up a slope.  Air coming down the far side of a range has little
+//! left to give, leaving a rain shadow.
+//!
+//! Seasons follow the sun's declination: summers warm and winters cool more
+//! towards the poles and away from the sea, and the rain belts (the tropical
+//! rain band, the dry subtropical highs and the mid-latitude storm tracks)
+//! drift north and south through the year, bringing monsoons and dry
+//! Mediterranean summers.
+
+use crate::terrain::DynGrid;
+use serde::{Deserialize, Serialize};
+
+/// Wind driven moisture parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MoistureModel {
+    /// Fraction of the air's missing moisture picked up per ocean tile.
+    pub evaporation: f64,
+    /// Fraction of the air's moisture rained out per tile on the flat.
+    pub rain_rate: f64,
+    /// Extra fraction rained out per unit of height climbed per tile, which
+    /// is also withheld on the way down.
+    pub orographic: f64,
+    /// Moisture of the air blowing in over the map edge, from 0 (dry) to 1
+    /// (saturated).
+    pub boundary_moisture: f64,
+    /// Weight of the modelled rainfall against the precipitation noise,
+    /// from 0 (noise only) to 1 (model only).
+    pub blend: f64,
+}
+
+impl Default for MoistureModel {
+    fn default() -> MoistureModel {
+        MoistureModel {
+            evaporation: 0.1,
+            rain_rate: 0.03,
+            orographic: 8.0,
+            boundary_moisture: 1.0,
+            blend: 0.7,
+        }
+    }
+}
+
+/// How the distance from the sea shapes the climate.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Continentality {
+    /// Distance from the coast in tiles beyond which the climate is fully
+    /// continental.
+    pub reach: f64,
+    /// How far the annual mean temperature on the coast is pulled in from
+    /// the extreme of its latitude, and inland pushed out past it, as a
+    /// fraction of the latitude's departure from the planet's mean
+    /// temperature.  The seasonal swing has its own coast to interior
+    /// contrast, `Seasons::maritime_swing`.
+    pub temperature_contrast: f64,
+    /// Fraction of the rainfall lost in fully continental interiors.
+    pub precipitation_loss: f64,
+}
+
+impl Default for Continentality {
+    fn default() -> Continentality {
+        Continentality {
+            reach: 20.0,
+            temperature_contrast: 0.3,
+            precipitation_loss: 0.5,
+        }
+    }
+}
+
+impl Continentality {
+    /// From 0 on the coast to 1 at `reach` tiles inland and beyond.
+    pub fn at(&self, coast_distance: f64) -> f64 {
+        (coast_distance / self.reach).min(1.0)
+    }
+}
+
+/// Where the map lies on the planet and how the planet is heated.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Planet {
+    /// Latitude in degrees of the map's top edge.
+    pub north_latitude: f64,
+    /// Latitude in degrees of the map's bottom edge.  Together with
+    /// `north_latitude` this places the equator, e.g. 30 and 60 for a
+    /// northern mid-latitude region or 0 and -90 for a southern hemisphere.
+    pub south_latitude: f64,
+    /// Tilt of the planet's axis in degrees, 0 for no seasons.
+    pub axial_tilt: f64,
+    /// Stretches the temperature difference between the equator and the
+    /// poles about the planet's mean temperature, which it leaves alone, 1
+    /// for an Earth-like planet.
+    pub temperature_scale: f64,
+}
+
+impl Default for Planet {
+    fn default() -> Planet {
+        Planet {
+            north_latitude: 90.0,
+            south_latitude: -90.0,
+            axial_tilt: 23.44,
+            temperature_scale: 1.0,
+        }
+    }
+}
+
+impl Planet {
+    /// Latitude in degrees of the middle of row `y` on a map `height` rows
+    /// tall.
+    pub fn latitude(&self, y: usize, height: usize) -> f64 {
+        let span = self.north_latitude - self.south_latitude;
+        self.north_latitude - span * (y as f64 + 0.5) / height as f64
+    }
+
+    /// Annual temperature map value from latitude alone, before height and
+    /// noise: 0.8 at the equator down to -0.5 at the poles on an Earth-like
+    /// planet, with `temperature_scale` stretching that about the mean.
+    pub fn base_temperature(&self, latitude: f64) -> f64 {
+        let mean = self.mean_temperature();
+        mean + self.temperature_scale * (earth_temperature(latitude) - mean)
+    }
+
+    /// `base_temperature` averaged over the whole planet's surface, not just
+    /// the map, whatever the `temperature_scale`.  It is reached about 33
+    /// degrees from the equator.
+    pub fn mean_temperature(&self) -> f64 {
+        // Mean distance from the equator weighted by the area of each band,
+        // pi / 2 - 1 radians
+        earth_temperature((std::f64::consts::FRAC_PI_2 - 1.0).to_degrees())
+    }
+}
+
+/// Earth's annual temperature map value at `latitude`.
+fn earth_temperature(latitude: f64) -> f64 {
+    0.8 - 1.3 * latitude.abs() / 90.0
+}
+
+/// How the climate noise maps onto real temperatures and rainfall, and how
+/// much colder it gets with height.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClimateUnits {
+    /// Sea level annual mean temperature in °C at the cold end of the
+    /// temperature noise.
+    pub min_temperature: f64,
+    /// Sea level annual mean temperature in °C at the warm end.
+    pub max_temperature: f64,
+    /// Annual rainfall in cm at the wet end of the precipitation noise on a
+    /// tile as warm as `max_temperature`, the dry end being none at all.
+    pub max_precipitation: f64,
+    /// Fraction less rainfall for every °C colder than `max_temperature`,
+    /// compounding.  Air holds about 7% less water per °C colder (the
+    /// Clausius–Clapeyron relation), and so has less to rain out.
+    pub cold_drying: f64,
+    /// Cooling in °C per 1000 m climbed.
+    pub lapse_rate: f64,
+    /// Metres per unit of height map above sea level.
+    pub elevation_scale: f64,
+}
+
+impl Default for ClimateUnits {
+    fn default() -> ClimateUnits {
+        ClimateUnits {
+            min_temperature: -10.0,
+            max_temperature: 32.0,
+            max_precipitation: 800.0,
+            cold_drying: 0.07,
+            lapse_rate: 6.5,
+            elevation_scale: 4000.0,
+        }
+    }
+}
+
+impl ClimateUnits {
+    /// °C for a temperature map value in [-1, 1].
+    pub fn degrees_c(&self, value: f64) -> f64 {
+        let span = self.max_temperature - self.min_temperature;
+        self.min_temperature + span * (value + 1.0) / 2.0
+    }
+
+    /// Rainfall in cm a year for a precipitation map value in [-1, 1] on a
+    /// tile at `temperature` °C.
+    pub fn rainfall_cm(&self, value: f64, temperature: f64) -> f64 {
+        let chill = (self.max_temperature - temperature).max(0.0);
+        let moisture = (1.0 + self.cold_drying).powf(-chill);
+        self.max_precipitation * (value + 1.0) / 2.0 * moisture
+    }
+
+    /// Cooling in °C at `height`, counting up from `sea_level`.
+    pub fn lapse_cooling(&self, height: f64, sea_level: f64) -> f64 {
+        let metres = (height - sea_level).max(0.0) * self.elevation_scale;
+        self.lapse_rate * metres / 1000.0
+    }
+}
+
+/// Seasonal cycle parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Seasons {
+    /// Seasonal temperature swing in °C: a month differs from the annual
+    /// mean by this times the sines of the latitude and of the sun's
+    /// declination.
+    pub temperature_swing: f64,
+    /// Share of the swing felt on the coast, the full swing only being
+    /// reached in continental interiors.
+    pub maritime_swing: f64,
+    /// How far the rain belts follow the sun, as a fraction of its
+    /// declination.
+    pub rain_belt_shift: f64,
+}
+
+impl Default for Seasons {
+    fn default() -> Seasons {
+        Seasons {
+            temperature_swing: 60.0,
+            maritime_swing: 0.3,
+            rain_belt_shift: 0.5,
+        }
+    }
+}
+
+pub const MONTHS: usize = 12;
+
+/// Precipitation map value where saturated air rains on flat ground.
+const SATURATED_FLAT: f64 = 0.3;
+
+/// Blur passes over the surface the wind climbs.
+const SMOOTHING: usize = 3;
+
+/// Passes over the map.  Each one carries moisture all the way along the
+/// east-west winds, but only partly along their north-south drift.
+const SWEEPS: usize = 4;
+
+/// Unit vector the prevailing wind blows along at `latitude`, in map
+/// coordinates (x east, y south).
+pub fn prevailing_wind(latitude: f64) -> (f64, f64) {
+    let band = latitude.abs();
+    // Direction of the nearer pole along y
+    let poleward = -latitude.signum();
+    let (east, south) = if band < 30.0 {
+        // Trade winds, blowing towards the equator
+        (-1.0, -0.5 * poleward)
+    } else if band < 60.0 {
+        // Westerlies
+        (1.0, 0.5 * poleward)
+    } else {
+        // Polar easterlies
+        (-1.0, -0.5 * poleward)
+    };
+    let len: f64 = (east * east + south * south).sqrt();
+    (east / len, south / len)
+}
+
+/// Latitude in degrees where the sun is overhead in the middle of `month`,
+/// January being 0.
+pub fn solar_declination(month: usize, axial_tilt: f64) -> f64 {
+    -axial_tilt * (std::f64::consts::TAU * (month as f64 + 0.5) / MONTHS as f64).cos()
+}
+
+/// How much warmer than the annual mean `month` is at `latitude`, in °C,
+/// for a continentality from 0 (coast) to 1 (interior).
+pub fn temperature_anomaly(
+    latitude: f64,
+    month: usize,
+    continentality: f64,
+    planet: &Planet,
+    params: &Seasons,
+) -> f64 {
+    let declination = solar_declination(month, planet.axial_tilt).to_radians();
+    let exposure = params.maritime_swing + (1.0 - params.maritime_swing) * continentality;
+    params.temperature_swing * declination.sin() * latitude.to_radians().sin() * exposure
+}
+
+/// Relative rainfall at `latitude` in `month` from the rain belts.
+fn rain_weight(latitude: f64, month: usize, planet: &Planet, params: &Seasons) -> f64 {
+    let shift = params.rain_belt_shift * solar_declination(month, planet.axial_tilt);
+    let belt = |center: f64, width: f64| (-((latitude - center) / width).powi(2)).exp();
+    let tropical = belt(shift, 12.0);
+    let storm_tracks = belt(50.0 + shift, 15.0) + belt(-50.0 + shift, 15.0);
+    let highs = belt(30.0 + shift, 10.0).max(belt(-30.0 + shift, 10.0));
+    (0.3 + 1.5 * tropical + 0.6 * storm_tracks) * (1.0 - 0.8 * highs)
+}
+
+/// Monthly mean temperature (°C) and precipitation (cm) grids, January
+/// first, spreading the annual values over the year.
+pub fn monthly_climate(
+    annual_temperature: &DynGrid<f64>,
+    annual_precipitation: &DynGrid<f64>,
+    continentality: &DynGrid<f64>,
+    planet: &Planet,
+    params: &Seasons,
+) -> (Vec<DynGrid<f64>>, Vec<DynGrid<f64>>) {
+    let (width, height) = (annual_temperature.width(), annual_temperature.height());
+    let mut temperature = vec![DynGrid::new(width, height, 0.0); MONTHS];
+    let mut precipitation = vec![DynGrid::new(width, height, 0.0); MONTHS];
+
+    for y in 0..height {
+        let lat = planet.latitude(y, height);
+        let weights: Vec<f64> = (0..MONTHS)
+            .map(|m| rain_weight(lat, m, planet, params))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        for x in 0..width {
+            for month in 0..MONTHS {
+                temperature[month][x][y] = annual_temperature[x][y]
+                    + temperature_anomaly(lat, month, continentality[x][y], planet, params);
+                precipitation[month][x][y] = annual_precipitation[x][y] * weights[month] / total;
+            }
+        }
+    }
+
+    (temperature, precipitation)
+}
+
+/// Distance in tiles from every tile to the nearest tile below `sea_level`,
+/// 0 at sea and infinite everywhere on maps without any.
+///
+/// A two pass chamfer transform, with diagonal steps counting as sqrt 2.
+pub fn coast_distance(height: &DynGrid<f64>, sea_level: f64) -> DynGrid<f64> {
+    let (width, depth) = (height.width(), height.height());
+    let mut distance = DynGrid::new(width, depth, f64::INFINITY);
+    for x in 0..width {
+        for y in 0..depth {
+            if height[x][y] < sea_level {
+                distance[x][y] = 0.0;
+            }
+        }
+    }
+
+    let diagonal = std::f64::consts::SQRT_2;
+    // Neighbors already visited by a top-left to bottom-right sweep
+    let before = [
+        (-1, -1, diagonal),
+        (0, -1, 1.0),
+        (1, -1, diagonal),
+        (-1, 0, 1.0),
+    ];
+    let relax = |distance: &mut DynGrid<f64>, x: usize, y: usize, sign: isize| {
+        for (dx, dy, step) in before.iter() {
+            let (nx, ny) = (x as isize + sign * dx, y as isize + sign * dy);
+            if nx < 0 || ny < 0 || nx >= width as isize || ny >= depth as isize {
+                continue;
+            }
+            let through = distance[nx as usize][ny as usize] + step;
+            if through < distance[x][y] {
+                distance[x][y] = through;
+            }
+        }
+    };
+    for y in 0..depth {
+        for x in 0..width {
+            relax(&mut distance, x, y, 1);
+        }
+    }
+    for y in (0..depth).rev() {
+        for x in (0..width).rev() {
+            relax(&mut distance, x, y, -1);
+        }
+    }
+
+    distance
+}
+
+/// Bilinear sample of `grid` at a point between tile centers, `None` off
+/// the map.
+pub(crate) fn sample(grid: &DynGrid<f64>, pos: (f64, f64)) -> Option<f64> {
+    let (max_x, max_y) = ((grid.width() - 1) as f64, (grid.height() - 1) as f64);
+    if pos.0 < 0.0 || pos.1 < 0.0 || pos.0 > max_x || pos.1 > max_y {
+        return None;
+    }
+    let (x0, y0) = (pos.0 as usize, pos.1 as usize);
+    let (x1, y1) = (
+        (x0 + 1).min(grid.width() - 1),
+        (y0 + 1).min(grid.height() - 1),
+    );
+    let (u, v) = (pos.0 - x0 as f64, pos.1 - y0 as f64);
+    Some(
+        grid[x0][y0] * (1.0 - u) * (1.0 - v)
+            + grid[x1][y0] * u * (1.0 - v)
+            + grid[x0][y1] * (1.0 - u) * v
+            + grid[x1][y1] * u * v,
+    )
+}
+
+/// Rainfall left by the prevailing winds on every tile, on the [-1, 1] scale
+/// of the precipitation map.
+pub fn wind_precipitation(
+    height: &DynGrid<f64>,
+    sea_level: f64,
+    planet: &Planet,
+    params: &MoistureModel,
+) -> DynGrid<f64> {
+    let (width, depth) = (height.width(), height.height());
+    // The sea surface is what the wind blows over, not the sea floor, and
+    // air rises over whole ranges rather than every bump
+    let mut surface = height.clone();
+    for x in 0..width {
+        for y in 0..depth {
+            surface[x][y] = surface[x][y].max(sea_level);
+        }
+    }
+    for _ in 0..SMOOTHING {
+        let mut blurred = surface.clone();
+        for x in 0..width {
+            for y in 0..depth {
+                let neighbors = surface.get_neighbors(&(x, y));
+                let sum: f64 = neighbors.iter().map(|n| surface[n.0][n.1]).sum();
+                blurred[x][y] = (sum + surface[x][y]) / (neighbors.len() + 1) as f64;
+            }
+        }
+        surface = blurred;
+    }
+
+    // Moisture of the air leaving each tile, downwind
+    let mut air = DynGrid::new(width, depth, params.boundary_moisture);
+    let mut rain = DynGrid::new(width, depth, 0.0);
+
+    for sweep in 0..SWEEPS {
+        // Alternate row order so the north-south drift spreads both ways
+        let rows: Vec<usize> = if sweep % 2 == 0 {
+            (0..depth).collect()
+        } else {
+            (0..depth).rev().collect()
+        };
+        for y in rows {
+            let wind = prevailing_wind(planet.latitude(y, depth));
+            // Walk each row downwind, so the upwind tile is already done
+            let columns: Vec<usize> = if wind.0 > 0.0 {
+                (0..width).collect()
+            } else {
+                (0..width).rev().collect()
+            };
+            for x in columns {
+                let upwind = (x as f64 - wind.0, y as f64 - wind.1);
+                let incoming = sample(&air, upwind).unwrap_or(params.boundary_moisture);
+                let climb = surface[x][y] - sample(&surface, upwind).unwrap_or(surface[x][y]);
+
+                let mut moisture = incoming;
+                if height[x][y] < sea_level {
+                    moisture += params.evaporation * (1.0 - moisture);
+                }
+                let fraction = (params.rain_rate + params.orographic * climb).clamp(0.0, 1.0);
+                let relative = moisture * fraction / params.rain_rate;
+                rain[x][y] = ((SATURATED_FLAT + 1.0) * relative - 1.0).min(1.0);
+                air[x][y] = moisture - moisture * fraction;
+            }
+        }
+    }
+
+    rain
+}
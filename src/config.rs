@@ -0,0 +1,693 @@
+use crate::bathymetry::Bathymetry;
+use crate::biomes::BiomeTable;
+use crate::climate::{ClimateUnits, Continentality, MoistureModel, Planet, Seasons};
+use crate::currents::OceanCurrents;
+use crate::erosion::{HydraulicErosion, ThermalErosion};
+use crate::heightmap::HeightmapFile;
+use crate::noise_graph::{Fractal, NoiseNode, MAX_OCTAVES};
+use crate::plates::TectonicPlates;
+use crate::shapes::{self, ShapeMask};
+use crate::terrain::AutoGenConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// On-disk config file formats, picked by file extension.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Result<Format, ConfigError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(Format::Toml),
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(Format::Json),
+            _ => Err(ConfigError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    UnknownFormat(PathBuf),
+    TomlParse(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+    Json(serde_json::Error),
+    ZeroDimension {
+        field: &'static str,
+    },
+    NonPositive {
+        field: &'static str,
+        value: f64,
+    },
+    Negative {
+        field: &'static str,
+        value: f64,
+    },
+    NonFinite {
+        field: &'static str,
+        value: f64,
+    },
+    OceanHeightOutOfRange(f64),
+    NotAFraction {
+        field: &'static str,
+        value: f64,
+    },
+    OutOfRange {
+        field: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    InvalidNoise {
+        reason: &'static str,
+    },
+    MaskImage {
+        path: PathBuf,
+        reason: String,
+    },
+    MaskWithoutOcean,
+    Heightmap {
+        path: PathBuf,
+        reason: String,
+    },
+    EmptyBiomeRule {
+        index: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to access config file: {}", e),
+            ConfigError::UnknownFormat(path) => write!(
+                f,
+                "can't tell config format of {}, expected a .toml or .json extension",
+                path.display()
+            ),
+            ConfigError::TomlParse(e) => write!(f, "invalid TOML config: {}", e),
+            ConfigError::TomlWrite(e) => write!(f, "failed to write TOML config: {}", e),
+            ConfigError::Json(e) => write!(f, "invalid JSON config: {}", e),
+            ConfigError::ZeroDimension { field } => write!(f, "{} must be at least 1", field),
+            ConfigError::NonPositive { field, value } => {
+                write!(f, "{} must be positive, got {}", field, value)
+            }
+            ConfigError::Negative { field, value } => {
+                write!(f, "{} must not be negative, got {}", field, value)
+            }
+            ConfigError::NonFinite { field, value } => {
+                write!(f, "{} must be a finite number, got {}", field, value)
+            }
+            ConfigError::OceanHeightOutOfRange(value) => {
+                write!(f, "ocean_height must be within [-1, 1], got {}", value)
+            }
+            ConfigError::NotAFraction { field, value } => {
+                write!(f, "{} must be within [0, 1], got {}", field, value)
+            }
+            ConfigError::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{} must be within [{}, {}], got {}",
+                field, min, max, value
+            ),
+            ConfigError::InvalidNoise { reason } => write!(f, "invalid noise graph: {}", reason),
+            ConfigError::MaskImage { path, reason } => {
+                write!(f, "can't read mask image {}: {}", path.display(), reason)
+            }
+            ConfigError::MaskWithoutOcean => write!(
+                f,
+                "shape masks and ocean_border need an ocean_height above -1 or a land_fraction"
+            ),
+            ConfigError::Heightmap { path, reason } => {
+                write!(f, "can't read heightmap {}: {}", path.display(), reason)
+            }
+            ConfigError::EmptyBiomeRule { index } => write!(
+                f,
+                "biome rule {} has min_temp >= max_temp and can never match",
+                index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::TomlParse(e) => Some(e),
+            ConfigError::TomlWrite(e) => Some(e),
+            ConfigError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> ConfigError {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> ConfigError {
+        ConfigError::TomlParse(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> ConfigError {
+        ConfigError::TomlWrite(e)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(e: serde_json::Error) -> ConfigError {
+        ConfigError::Json(e)
+    }
+}
+
+fn check_finite(field: &'static str, value: f64) -> Result<(), ConfigError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(ConfigError::NonFinite { field, value })
+    }
+}
+
+fn check_range(field: &'static str, value: f64, min: f64, max: f64) -> Result<(), ConfigError> {
+    check_finite(field, value)?;
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(ConfigError::OutOfRange {
+            field,
+            value,
+            min,
+            max,
+        })
+    }
+}
+
+fn check_octaves(field: &'static str, value: usize) -> Result<(), ConfigError> {
+    if value == 0 {
+        return Err(ConfigError::ZeroDimension { field });
+    }
+    check_range(field, value as f64, 1.0, MAX_OCTAVES as f64)
+}
+
+fn check_fraction(field: &'static str, value: f64) -> Result<(), ConfigError> {
+    check_finite(field, value)?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(ConfigError::NotAFraction { field, value })
+    }
+}
+
+fn check_positive(field: &'static str, value: f64) -> Result<(), ConfigError> {
+    check_finite(field, value)?;
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::NonPositive { field, value })
+    }
+}
+
+fn check_non_negative(field: &'static str, value: f64) -> Result<(), ConfigError> {
+    check_finite(field, value)?;
+    if value >= 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::Negative { field, value })
+    }
+}
+
+impl AutoGenConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive("x_scale", self.x_scale)?;
+        check_positive("y_scale", self.y_scale)?;
+        check_positive("landmass_frequency", self.landmass_frequency)?;
+        check_positive("precip_frequency", self.precip_frequency)?;
+        check_positive("temperature_frequency", self.temperature_frequency)?;
+        check_finite("precip_offset", self.precip_offset)?;
+        check_finite("temperature_offset", self.temperature_offset)?;
+        check_finite("ocean_height", self.ocean_height)?;
+        if !(-1.0..=1.0).contains(&self.ocean_height) {
+            return Err(ConfigError::OceanHeightOutOfRange(self.ocean_height));
+        }
+        check_positive("river_threshold", self.river_threshold)?;
+        check_positive("lake_min_depth", self.lake_min_depth)?;
+        check_non_negative("lake_evaporation", self.lake_evaporation)?;
+        if let Some(fraction) = self.land_fraction {
+            check_fraction("land_fraction", fraction)?;
+        }
+        for graph in [
+            &self.height_noise,
+            &self.precip_noise,
+            &self.temperature_noise,
+        ]
+        .iter()
+        .copied()
+        .flatten()
+        {
+            graph.validate()?;
+        }
+        if let Some(heightmap) = &self.heightmap {
+            heightmap.validate()?;
+        }
+        if let Some(shape) = &self.shape {
+            shape.validate()?;
+        }
+        // A land fraction always leaves masked out tiles under water
+        let has_ocean = self.land_fraction.is_some() || self.ocean_height > -1.0;
+        if (self.shape.is_some() || self.ocean_border > 0) && !has_ocean {
+            return Err(ConfigError::MaskWithoutOcean);
+        }
+        self.bathymetry.validate()?;
+        if let Some(plates) = &self.plates {
+            plates.validate()?;
+        }
+        if let Some(erosion) = &self.hydraulic_erosion {
+            erosion.validate()?;
+        }
+        if let Some(erosion) = &self.thermal_erosion {
+            erosion.validate()?;
+        }
+        if let Some(moisture) = &self.moisture {
+            moisture.validate()?;
+        }
+        if let Some(continentality) = &self.continentality {
+            continentality.validate()?;
+        }
+        self.planet.validate()?;
+        self.units.validate()?;
+        if let Some(currents) = &self.currents {
+            currents.validate()?;
+        }
+        if let Some(seasons) = &self.seasons {
+            seasons.validate()?;
+        }
+        self.biomes.validate()
+    }
+}
+
+impl BiomeTable {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            let temps = rule.min_temp.iter().chain(rule.max_temp.iter());
+            for t in temps {
+                check_finite("biome rule temperature", *t)?;
+            }
+            let precips = rule.min_precip.iter().chain(rule.max_precip.iter());
+            for p in precips {
+                check_finite("biome rule precipitation", p.base)?;
+                check_finite("biome rule precipitation", p.per_degree)?;
+            }
+            if let (Some(min), Some(max)) = (rule.min_temp, rule.max_temp) {
+                if min >= max {
+                    return Err(ConfigError::EmptyBiomeRule { index });
+                }
+            }
+        }
+
+        let land = &self.landforms;
+        check_finite("alpine_height", land.alpine_height)?;
+        check_finite("montane_height", land.montane_height)?;
+        check_finite("beach_height", land.beach_height)?;
+        check_finite("mangrove_min_temp", land.mangrove_min_temp)?;
+        check_finite("mangrove_min_precip", land.mangrove_min_precip)?;
+        check_finite("wetland_min_precip", land.wetland_min_precip)?;
+        check_finite("wetland_min_temp", land.wetland_min_temp)?;
+        check_finite("wetland_max_relief", land.wetland_max_relief)?;
+        Ok(())
+    }
+}
+
+impl Fractal {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive("frequency", self.frequency)?;
+        check_octaves("octaves", self.octaves)?;
+        check_positive("lacunarity", self.lacunarity)?;
+        check_finite("persistence", self.persistence)?;
+        Ok(())
+    }
+}
+
+impl NoiseNode {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            NoiseNode::Fbm(f) | NoiseNode::RidgedMulti(f) | NoiseNode::Billow(f) => f.validate(),
+            NoiseNode::Perlin { frequency } | NoiseNode::Worley { frequency, .. } => {
+                check_positive("frequency", *frequency)
+            }
+            NoiseNode::Constant { value } => check_finite("value", *value),
+            NoiseNode::Add { sources } | NoiseNode::Multiply { sources } => {
+                if sources.is_empty() {
+                    return Err(ConfigError::InvalidNoise {
+                        reason: "add and multiply need at least one source",
+                    });
+                }
+                sources.iter().try_for_each(|s| s.validate())
+            }
+            NoiseNode::Select {
+                outside,
+                inside,
+                control,
+                lower_bound,
+                upper_bound,
+                falloff,
+            } => {
+                check_finite("lower_bound", *lower_bound)?;
+                check_finite("upper_bound", *upper_bound)?;
+                check_finite("falloff", *falloff)?;
+                if lower_bound >= upper_bound {
+                    return Err(ConfigError::InvalidNoise {
+                        reason: "select lower_bound must be below upper_bound",
+                    });
+                }
+                outside.validate()?;
+                inside.validate()?;
+                control.validate()
+            }
+            NoiseNode::Terrace {
+                source,
+                control_points,
+                ..
+            } => {
+                for p in control_points {
+                    check_finite("control_points", *p)?;
+                }
+                let mut distinct = control_points.clone();
+                distinct.sort_by(f64::total_cmp);
+                distinct.dedup();
+                if distinct.len() < 2 {
+                    return Err(ConfigError::InvalidNoise {
+                        reason: "terrace needs at least two distinct control points",
+                    });
+                }
+                source.validate()
+            }
+            NoiseNode::Warp {
+                source,
+                frequency,
+                power,
+                roughness,
+            } => {
+                check_positive("frequency", *frequency)?;
+                check_finite("power", *power)?;
+                check_octaves("roughness", *roughness)?;
+                source.validate()
+            }
+            NoiseNode::ScaleBias {
+                source,
+                scale,
+                bias,
+            } => {
+                check_finite("scale", *scale)?;
+                check_finite("bias", *bias)?;
+                source.validate()
+            }
+        }
+    }
+}
+
+impl MoistureModel {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_fraction("evaporation", self.evaporation)?;
+        check_fraction("rain_rate", self.rain_rate)?;
+        check_positive("rain_rate", self.rain_rate)?;
+        check_finite("orographic", self.orographic)?;
+        check_fraction("boundary_moisture", self.boundary_moisture)?;
+        check_fraction("blend", self.blend)
+    }
+}
+
+impl Continentality {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive("reach", self.reach)?;
+        check_fraction("temperature_contrast", self.temperature_contrast)?;
+        check_fraction("precipitation_loss", self.precipitation_loss)
+    }
+}
+
+impl Planet {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_range("north_latitude", self.north_latitude, -90.0, 90.0)?;
+        // The map runs north to south, so the bottom edge can't be further north
+        let north = self.north_latitude;
+        check_range("south_latitude", self.south_latitude, -90.0, north)?;
+        check_range("axial_tilt", self.axial_tilt, 0.0, 90.0)?;
+        check_finite("temperature_scale", self.temperature_scale)
+    }
+}
+
+impl ClimateUnits {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_finite("min_temperature", self.min_temperature)?;
+        check_finite("max_temperature", self.max_temperature)?;
+        let span = self.max_temperature - self.min_temperature;
+        check_positive("max_temperature - min_temperature", span)?;
+        check_positive("max_precipitation", self.max_precipitation)?;
+        check_fraction("cold_drying", self.cold_drying)?;
+        check_finite("lapse_rate", self.lapse_rate)?;
+        check_positive("elevation_scale", self.elevation_scale)
+    }
+}
+
+impl Bathymetry {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive("shelf_depth", self.shelf_depth)?;
+        check_positive("shelf_width", self.shelf_width)?;
+        check_positive("abyss_depth", self.abyss_depth)?;
+        check_positive("trench_relief", self.trench_relief)
+    }
+}
+
+impl OceanCurrents {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive("boundary_width", self.boundary_width)?;
+        check_fraction("exchange", self.exchange)?;
+        check_positive("reach", self.reach)?;
+        check_finite("precipitation_effect", self.precipitation_effect)
+    }
+}
+
+impl Seasons {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_finite("temperature_swing", self.temperature_swing)?;
+        check_fraction("maritime_swing", self.maritime_swing)?;
+        check_finite("rain_belt_shift", self.rain_belt_shift)
+    }
+}
+
+impl HeightmapFile {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for value in self.low.iter().chain(self.high.iter()) {
+            check_finite("heightmap range", *value)?;
+        }
+        self.load(1, 1).map(|_| ())
+    }
+}
+
+impl ShapeMask {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            ShapeMask::Island { radius, falloff } => {
+                check_positive("radius", *radius)?;
+                check_positive("falloff", *falloff)
+            }
+            ShapeMask::Pangaea { radius, uplift } => {
+                check_positive("radius", *radius)?;
+                check_finite("uplift", *uplift)
+            }
+            ShapeMask::Archipelago { radius, .. } => check_positive("radius", *radius),
+            ShapeMask::Image { path } => shapes::load_image(path, 1, 1).map(|_| ()),
+        }
+    }
+}
+
+impl TectonicPlates {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.plate_count == 0 {
+            return Err(ConfigError::ZeroDimension {
+                field: "plate_count",
+            });
+        }
+        check_fraction("continental_fraction", self.continental_fraction)?;
+        check_finite("continental_height", self.continental_height)?;
+        check_finite("oceanic_height", self.oceanic_height)?;
+        check_finite("mountain_height", self.mountain_height)?;
+        check_finite("rift_depth", self.rift_depth)?;
+        check_finite("trench_depth", self.trench_depth)?;
+        check_positive("boundary_width", self.boundary_width)?;
+        check_finite("boundary_noise", self.boundary_noise)?;
+        check_fraction("blend", self.blend)?;
+        Ok(())
+    }
+}
+
+impl HydraulicErosion {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_fraction("erosion_rate", self.erosion_rate)?;
+        check_fraction("deposition_rate", self.deposition_rate)?;
+        check_fraction("evaporation", self.evaporation)?;
+        check_fraction("inertia", self.inertia)?;
+        check_positive("capacity", self.capacity)?;
+        check_finite("min_slope", self.min_slope)?;
+        check_positive("gravity", self.gravity)?;
+        Ok(())
+    }
+}
+
+impl ThermalErosion {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_non_negative("talus_angle", self.talus_angle)?;
+        check_fraction("rate", self.rate)?;
+        Ok(())
+    }
+}
+
+/// Named starting points for `AutoGenConfig`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Default,
+    Continents,
+    Islands,
+    Arid,
+    Frozen,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [
+        Preset::Default,
+        Preset::Continents,
+        Preset::Islands,
+        Preset::Arid,
+        Preset::Frozen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Continents => "continents",
+            Preset::Islands => "islands",
+            Preset::Arid => "arid",
+            Preset::Frozen => "frozen",
+        }
+    }
+
+    pub fn config(&self) -> AutoGenConfig {
+        let default = AutoGenConfig::default();
+        match self {
+            Preset::Default => default,
+            Preset::Continents => AutoGenConfig {
+                landmass_frequency: 2.0,
+                ocean_height: -0.1,
+                ..default
+            },
+            Preset::Islands => AutoGenConfig {
+                landmass_frequency: 6.0,
+                ocean_height: 0.25,
+                river_threshold: 10.0,
+                ..default
+            },
+            Preset::Arid => AutoGenConfig {
+                precip_offset: -0.6,
+                temperature_offset: 0.3,
+                ocean_height: -0.4,
+                ..default
+            },
+            Preset::Frozen => AutoGenConfig {
+                temperature_offset: -0.6,
+                ocean_height: -0.2,
+                ..default
+            },
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Preset, String> {
+        Preset::ALL
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown preset '{}'", s))
+    }
+}
+
+/// Everything needed to generate a world: map dimensions plus the
+/// generation parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub width: usize,
+    pub height: usize,
+    pub autogen: AutoGenConfig,
+}
+
+impl Default for WorldConfig {
+    fn default() -> WorldConfig {
+        WorldConfig {
+            width: 140,
+            height: 60,
+            autogen: AutoGenConfig::default(),
+        }
+    }
+}
+
+impl WorldConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.width == 0 {
+            return Err(ConfigError::ZeroDimension { field: "width" });
+        }
+        if self.height == 0 {
+            return Err(ConfigError::ZeroDimension { field: "height" });
+        }
+        self.autogen.validate()
+    }
+
+    /// Parse and validate a config in the given format.
+    pub fn from_str(contents: &str, format: Format) -> Result<WorldConfig, ConfigError> {
+        let config: WorldConfig = match format {
+            Format::Toml => toml::from_str(contents)?,
+            Format::Json => serde_json::from_str(contents)?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_string(&self, format: Format) -> Result<String, ConfigError> {
+        match format {
+            Format::Toml => Ok(toml::to_string_pretty(self)?),
+            Format::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// Load a config file, with the format picked from its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WorldConfig, ConfigError> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+        WorldConfig::from_str(&fs::read_to_string(path)?, format)
+    }
+
+    /// Save a config file, with the format picked from its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+        fs::write(path, self.to_string(format)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Surface ocean currents and the warmth or chill they bring to the coasts.
+//!
+//! Each ocean is stirred into gyres by the wind bands: water runs west with
+//! the trade winds and polar easterlies and east with the westerlies.  Near a
+//! coast it turns to run along it instead.  In the subtropical gyres,
+//! equatorward of 45 degrees, that means warm currents heading poleward
+//! along the ocean's western side and cold ones heading back to the equator
+//! along its eastern side.  The subpolar gyres beyond turn the other way.
+//! Water carries its temperature along, slowly taking on that of the
+//! latitude it reaches, and the difference warms or cools the air over the
+//! nearby land.
+
+use crate::climate::{self, ClimateUnits, Planet};
+use crate::terrain::DynGrid;
+use serde::{Deserialize, Serialize};
+
+/// Ocean current parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OceanCurrents {
+    /// Distance in tiles from a coast over which the water turns to run
+    /// along it.
+    pub boundary_width: f64,
+    /// Fraction of its difference from the local sea temperature the water
+    /// loses per tile travelled.
+    pub exchange: f64,
+    /// Tiles inland over which a current's effect on the air falls by a
+    /// factor of e.
+    pub reach: f64,
+    /// Change in the precipitation map value per °C of current anomaly,
+    /// warm currents bringing rain and cold ones drought.
+    pub precipitation_effect: f64,
+}
+
+impl Default for OceanCurrents {
+    fn default() -> OceanCurrents {
+        OceanCurrents {
+            boundary_width: 6.0,
+            exchange: 0.15,
+            reach: 4.0,
+            precipitation_effect: 0.03,
+        }
+    }
+}
+
+/// Latitude splitting the subtropical gyres from the subpolar ones.
+const GYRE_EDGE: f64 = 45.0;
+
+/// Passes over the map, alternating directions.
+const SWEEPS: usize = 8;
+
+/// Tiles to the nearest land in the -x and +x directions along row `y`, as
+/// far as `limit`.
+fn land_along_row(ocean: &DynGrid<bool>, x: usize, y: usize, limit: usize) -> (usize, usize) {
+    let width = ocean.width();
+    let west = (1..=limit)
+        .find(|d| x >= *d && !ocean[x - d][y])
+        .unwrap_or(usize::MAX);
+    let east = (1..=limit)
+        .find(|d| x + d < width && !ocean[x + d][y])
+        .unwrap_or(usize::MAX);
+    (west, east)
+}
+
+/// Unit vector the surface water flows along, in map coordinates (x east,
+/// y south).
+fn flow(ocean: &DynGrid<bool>, x: usize, y: usize, latitude: f64, width: f64) -> (f64, f64) {
+    let zonal = climate::prevailing_wind(latitude).0.signum();
+    // Direction of the nearer pole along y
+    let poleward = -latitude.signum();
+    let subtropical = latitude.abs() < GYRE_EDGE;
+
+    let (west, east) = land_along_row(ocean, x, y, width.ceil() as usize);
+    let (distance, along) = if west <= east {
+        // Western side of the ocean
+        let turn = if subtropical { poleward } else { -poleward };
+        (west, turn)
+    } else {
+        let turn = if subtropical { -poleward } else { poleward };
+        (east, turn)
+    };
+    if distance == usize::MAX {
+        return (zonal, 0.0);
+    }
+
+    let w = (1.0 - (distance as f64 - 1.0) / width).clamp(0.0, 1.0);
+    let (u, v) = (zonal * (1.0 - w), along * w);
+    let len = (u * u + v * v).sqrt();
+    (u / len, v / len)
+}
+
+/// Sea surface temperature in °C that `planet` has at `latitude` without
+/// any currents.
+fn still_water(planet: &Planet, units: &ClimateUnits, latitude: f64) -> f64 {
+    units.degrees_c(planet.base_temperature(latitude))
+}
+
+/// Temperature shift in °C the currents bring: the sea surface anomaly over
+/// `ocean` tiles, and its effect on the air over land, fading inland.
+pub fn current_anomaly(
+    ocean: &DynGrid<bool>,
+    planet: &Planet,
+    units: &ClimateUnits,
+    params: &OceanCurrents,
+) -> DynGrid<f64> {
+    let (width, depth) = (ocean.width(), ocean.height());
+    let equilibrium: Vec<f64> = (0..depth)
+        .map(|y| still_water(planet, units, planet.latitude(y, depth)))
+        .collect();
+
+    // Water temperature, land and the map edge holding still water
+    let mut water = DynGrid::new(width, depth, 0.0);
+    let mut velocity = DynGrid::new(width, depth, (0.0, 0.0));
+    for x in 0..width {
+        for y in 0..depth {
+            water[x][y] = equilibrium[y];
+            if ocean[x][y] {
+                let lat = planet.latitude(y, depth);
+                velocity[x][y] = flow(ocean, x, y, lat, params.boundary_width);
+            }
+        }
+    }
+
+    for sweep in 0..SWEEPS {
+        let columns: Vec<usize> = if sweep % 2 == 0 {
+            (0..width).collect()
+        } else {
+            (0..width).rev().collect()
+        };
+        let rows: Vec<usize> = if sweep % 4 < 2 {
+            (0..depth).collect()
+        } else {
+            (0..depth).rev().collect()
+        };
+        for &y in &rows {
+            for &x in &columns {
+                if !ocean[x][y] {
+                    continue;
+                }
+                let (u, v) = velocity[x][y];
+                let upstream = (x as f64 - u, y as f64 - v);
+                let incoming = climate::sample(&water, upstream).unwrap_or(equilibrium[y]);
+                water[x][y] = incoming + params.exchange * (equilibrium[y] - incoming);
+            }
+        }
+    }
+
+    let mut anomaly = DynGrid::new(width, depth, 0.0);
+    let mut reached = ocean.clone();
+    for x in 0..width {
+        for y in 0..depth {
+            if ocean[x][y] {
+                anomaly[x][y] = water[x][y] - equilibrium[y];
+            }
+        }
+    }
+
+    // Carry the anomaly inland a tile per pass, each tile taking the mean of
+    // its neighbors nearer the sea and fading as it goes
+    let fade = (-1.0 / params.reach).exp();
+    let passes = (3.0 * params.reach).ceil() as usize;
+    for _ in 0..passes {
+        let mut next = anomaly.clone();
+        let mut next_reached = reached.clone();
+        for x in 0..width {
+            for y in 0..depth {
+                if reached[x][y] {
+                    continue;
+                }
+                let from: Vec<(usize, usize)> = anomaly
+                    .get_neighbors(&(x, y))
+                    .into_iter()
+                    .filter(|n| reached[n.0][n.1])
+                    .collect();
+                if from.is_empty() {
+                    continue;
+                }
+                let mean = from.iter().map(|n| anomaly[n.0][n.1]).sum::<f64>() / from.len() as f64;
+                next[x][y] = fade * mean;
+                next_reached[x][y] = true;
+            }
+        }
+        anomaly = next;
+        reached = next_reached;
+    }
+
+    anomaly
+}
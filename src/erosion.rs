@@ -0,0 +1,240 @@
+//! Erosion passes that reshape a raw noise height map.
+//!
+//! Hydraulic erosion follows the droplet model of Hans Theobald Beyer,
+//! "Implementation of a method for hydraulic erosion" (2015): each droplet
+//! rolls downhill with some inertia, picking up sediment while it is moving
+//! fast and carrying little, and dropping it again in flats and pits.
+//!
+//! Thermal erosion is the talus model of Musgrave, Kolb & Mace, "The
+//! synthesis and rendering of eroded fractal terrains" (1989): wherever a
+//! slope is steeper than the talus angle, material slides down it until it
+//! is not.
+
+use crate::terrain::DynGrid;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Droplet hydraulic erosion parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HydraulicErosion {
+    /// Number of droplets simulated.
+    pub iterations: usize,
+    /// Fraction of a droplet's spare capacity it erodes per step.
+    pub erosion_rate: f64,
+    /// Fraction of a droplet's excess sediment it deposits per step.
+    pub deposition_rate: f64,
+    /// Fraction of a droplet's water lost per step.
+    pub evaporation: f64,
+    /// How much of its previous direction a droplet keeps, from 0 (always
+    /// straight downhill) to 1 (never turns).
+    pub inertia: f64,
+    /// Sediment a droplet can carry per unit of slope, speed and water.
+    pub capacity: f64,
+    /// Slope used for the capacity on flats, so they still erode a little.
+    pub min_slope: f64,
+    pub gravity: f64,
+    /// Steps a droplet lives for at most.
+    pub max_steps: usize,
+}
+
+impl Default for HydraulicErosion {
+    fn default() -> HydraulicErosion {
+        HydraulicErosion {
+            iterations: 50_000,
+            erosion_rate: 0.3,
+            deposition_rate: 0.3,
+            evaporation: 0.02,
+            inertia: 0.05,
+            capacity: 4.0,
+            min_slope: 0.01,
+            gravity: 4.0,
+            max_steps: 64,
+        }
+    }
+}
+
+/// Talus slope relaxation parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalErosion {
+    /// Number of passes over the whole map.
+    pub iterations: usize,
+    /// Steepest stable slope, in height units per tile (the tangent of the
+    /// talus angle).
+    pub talus_angle: f64,
+    /// Fraction of the material above the talus slope moved per pass.
+    pub rate: f64,
+}
+
+impl Default for ThermalErosion {
+    fn default() -> ThermalErosion {
+        ThermalErosion {
+            iterations: 50,
+            talus_angle: 0.03,
+            rate: 0.5,
+        }
+    }
+}
+
+/// Interpolated height and gradient at a point between tile centers.
+fn height_and_gradient(height: &DynGrid<f64>, pos: (f64, f64)) -> (f64, (f64, f64)) {
+    let (x, y) = (pos.0 as usize, pos.1 as usize);
+    let (u, v) = (pos.0 - x as f64, pos.1 - y as f64);
+
+    let nw = height[x][y];
+    let ne = height[x + 1][y];
+    let sw = height[x][y + 1];
+    let se = height[x + 1][y + 1];
+
+    let gx = (ne - nw) * (1.0 - v) + (se - sw) * v;
+    let gy = (sw - nw) * (1.0 - u) + (se - ne) * u;
+    let h = nw * (1.0 - u) * (1.0 - v) + ne * u * (1.0 - v) + sw * (1.0 - u) * v + se * u * v;
+    (h, (gx, gy))
+}
+
+/// Spread `amount` over the four tiles around `pos`, weighted bilinearly.
+/// Positive amounts raise the ground and build up `sediment`, negative ones
+/// cut into it, eating through any sediment first.
+fn deposit(height: &mut DynGrid<f64>, sediment: &mut DynGrid<f64>, pos: (f64, f64), amount: f64) {
+    let (x, y) = (pos.0 as usize, pos.1 as usize);
+    let (u, v) = (pos.0 - x as f64, pos.1 - y as f64);
+    let corners = [
+        (x, y, (1.0 - u) * (1.0 - v)),
+        (x + 1, y, u * (1.0 - v)),
+        (x, y + 1, (1.0 - u) * v),
+        (x + 1, y + 1, u * v),
+    ];
+    for (cx, cy, weight) in corners.iter() {
+        let delta = amount * weight;
+        height[*cx][*cy] += delta;
+        sediment[*cx][*cy] = (sediment[*cx][*cy] + delta).max(0.0);
+    }
+}
+
+/// Run droplet erosion over `height`, adding the material laid down to
+/// `sediment`.  Droplets stop when they reach `sea_level`, come to rest or
+/// run out of steps, and drop whatever they still carry where they stop.
+/// Only droplets running off the map take material with them.
+pub fn hydraulic_erosion<R: Rng>(
+    height: &mut DynGrid<f64>,
+    sediment: &mut DynGrid<f64>,
+    params: &HydraulicErosion,
+    sea_level: f64,
+    rng: &mut R,
+) {
+    let (width, depth) = (height.width(), height.height());
+    if width < 2 || depth < 2 {
+        return;
+    }
+    // Droplets stay inside the last cell so all four corners exist
+    let (max_x, max_y) = ((width - 1) as f64, (depth - 1) as f64);
+
+    for _ in 0..params.iterations {
+        let mut pos = (rng.gen_range(0.0..max_x), rng.gen_range(0.0..max_y));
+        let mut dir = (0.0, 0.0);
+        let mut speed = 1.0;
+        let mut water = 1.0;
+        let mut carried = 0.0;
+
+        for _ in 0..params.max_steps {
+            let (h, gradient) = height_and_gradient(height, pos);
+            if h < sea_level {
+                break;
+            }
+
+            dir = (
+                dir.0 * params.inertia - gradient.0 * (1.0 - params.inertia),
+                dir.1 * params.inertia - gradient.1 * (1.0 - params.inertia),
+            );
+            let len = (dir.0 * dir.0 + dir.1 * dir.1).sqrt();
+            if len == 0.0 {
+                break;
+            }
+            dir = (dir.0 / len, dir.1 / len);
+
+            let next = (pos.0 + dir.0, pos.1 + dir.1);
+            if next.0 < 0.0 || next.1 < 0.0 || next.0 >= max_x || next.1 >= max_y {
+                // Whatever it carries washes off the map
+                carried = 0.0;
+                break;
+            }
+
+            let dh = height_and_gradient(height, next).0 - h;
+            let capacity = (-dh).max(params.min_slope) * speed * water * params.capacity;
+
+            if dh > 0.0 {
+                // Going uphill: fill the pit behind, never above the next tile
+                let amount = dh.min(carried);
+                carried -= amount;
+                deposit(height, sediment, pos, amount);
+            } else if carried > capacity {
+                let amount = (carried - capacity) * params.deposition_rate;
+                carried -= amount;
+                deposit(height, sediment, pos, amount);
+            } else {
+                // Never dig deeper than the drop to the next position
+                let amount = ((capacity - carried) * params.erosion_rate).min(-dh);
+                carried += amount;
+                deposit(height, sediment, pos, -amount);
+            }
+
+            speed = (speed * speed - dh * params.gravity).max(0.0).sqrt();
+            water *= 1.0 - params.evaporation;
+            pos = next;
+        }
+
+        deposit(height, sediment, pos, carried);
+    }
+}
+
+/// Move material down every slope steeper than the talus angle, adding what
+/// lands at the foot of a slope to `sediment`.
+pub fn thermal_erosion(
+    height: &mut DynGrid<f64>,
+    sediment: &mut DynGrid<f64>,
+    params: &ThermalErosion,
+) {
+    let (width, depth) = (height.width(), height.height());
+    let mut delta = DynGrid::new(width, depth, 0.0);
+
+    for _ in 0..params.iterations {
+        for x in 0..width {
+            for y in 0..depth {
+                // Height each steeper-than-talus neighbor sits below its
+                // stable level
+                let mut excess = vec![];
+                for n in height.get_neighbors(&(x, y)) {
+                    let distance = if n.0 != x && n.1 != y {
+                        std::f64::consts::SQRT_2
+                    } else {
+                        1.0
+                    };
+                    let drop = height[x][y] - height[n.0][n.1] - params.talus_angle * distance;
+                    if drop > 0.0 {
+                        excess.push((n, drop));
+                    }
+                }
+
+                let total: f64 = excess.iter().map(|e| e.1).sum();
+                let max = excess.iter().map(|e| e.1).fold(0.0, f64::max);
+                // Half the steepest excess levels that slope, any more and
+                // the tile would end up below its neighbor
+                let moved = params.rate * max / 2.0;
+                for (n, drop) in excess {
+                    let amount = moved * drop / total;
+                    delta[x][y] -= amount;
+                    delta[n.0][n.1] += amount;
+                }
+            }
+        }
+
+        for x in 0..width {
+            for y in 0..depth {
+                height[x][y] += delta[x][y];
+                sediment[x][y] = (sediment[x][y] + delta[x][y]).max(0.0);
+                delta[x][y] = 0.0;
+            }
+        }
+    }
+}
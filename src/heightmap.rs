@@ -0,0 +1,219 @@
+//! Height maps imported from files instead of generated from noise.
+//!
+//! Greyscale PNGs (8 or 16 bit) and ESRI ASCII grids are read, scaled onto
+//! [-1, 1] and resampled to the map size.  The file's top row is the map's
+//! top row.
+
+use crate::config::ConfigError;
+use crate::terrain::DynGrid;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeightmapFile {
+    /// A `.png` image or `.asc` ESRI ASCII grid.
+    pub path: PathBuf,
+    /// Raw value mapped to height -1: the pixel value for images, the
+    /// elevation for grids.  Defaults to black, or the grid's lowest cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<f64>,
+    /// Raw value mapped to height 1.  Defaults to white, or the grid's
+    /// highest cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<f64>,
+}
+
+/// Raw cell values as read, `None` where the file has no data.
+struct RawGrid {
+    cells: DynGrid<Option<f64>>,
+    /// Range to scale onto [-1, 1] when the config doesn't give one.
+    range: (f64, f64),
+}
+
+impl HeightmapFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> HeightmapFile {
+        HeightmapFile {
+            path: path.into(),
+            low: None,
+            high: None,
+        }
+    }
+
+    fn error<S: ToString>(&self, reason: S) -> ConfigError {
+        ConfigError::Heightmap {
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Read the file as a height map of the given size.  Cells without
+    /// data become -1.
+    pub fn load(&self, width: usize, height: usize) -> Result<DynGrid<f64>, ConfigError> {
+        let extension = self.path.extension().and_then(|e| e.to_str());
+        let raw = match extension {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => read_png(&self.path)?,
+            Some(ext) if ext.eq_ignore_ascii_case("asc") => {
+                let text = fs::read_to_string(&self.path).map_err(|e| self.error(e))?;
+                read_ascii_grid(&text).map_err(|e| self.error(e))?
+            }
+            _ => return Err(self.error("unknown format, expected .png or .asc")),
+        };
+
+        let low = self.low.unwrap_or(raw.range.0);
+        let high = self.high.unwrap_or(raw.range.1);
+        if high <= low {
+            return Err(self.error(format!("no height range, low {} high {}", low, high)));
+        }
+
+        let (raw_width, raw_height) = (raw.cells.width(), raw.cells.height());
+        let mut scaled = DynGrid::new(raw_width, raw_height, -1.0);
+        for x in 0..raw_width {
+            for y in 0..raw_height {
+                if let Some(v) = raw.cells[x][y] {
+                    scaled[x][y] = (2.0 * (v - low) / (high - low) - 1.0).clamp(-1.0, 1.0);
+                }
+            }
+        }
+        Ok(resample(&scaled, width, height))
+    }
+}
+
+/// Bilinear resampling, with the corner cells of both grids lined up.
+fn resample(grid: &DynGrid<f64>, width: usize, height: usize) -> DynGrid<f64> {
+    let (src_width, src_height) = (grid.width(), grid.height());
+    // Position in the source grid of a destination coordinate
+    let scale = |i: usize, dst: usize, src: usize| {
+        if dst > 1 {
+            i as f64 * (src - 1) as f64 / (dst - 1) as f64
+        } else {
+            0.0
+        }
+    };
+
+    let mut out = DynGrid::new(width, height, 0.0);
+    for x in 0..width {
+        for y in 0..height {
+            let (sx, sy) = (scale(x, width, src_width), scale(y, height, src_height));
+            let (x0, y0) = (sx as usize, sy as usize);
+            let (x1, y1) = ((x0 + 1).min(src_width - 1), (y0 + 1).min(src_height - 1));
+            let (u, v) = (sx - x0 as f64, sy - y0 as f64);
+            out[x][y] = grid[x0][y0] * (1.0 - u) * (1.0 - v)
+                + grid[x1][y0] * u * (1.0 - v)
+                + grid[x0][y1] * (1.0 - u) * v
+                + grid[x1][y1] * u * v;
+        }
+    }
+    out
+}
+
+#[cfg(feature = "image")]
+fn read_png(path: &Path) -> Result<RawGrid, ConfigError> {
+    let image = image::open(path).map_err(|e| ConfigError::Heightmap {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+
+    let color = image.color();
+    let sixteen_bit = color.bytes_per_pixel() / color.channel_count() == 2;
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut cells = DynGrid::new(width, height, None);
+    let range = if sixteen_bit {
+        for (x, y, pixel) in image.to_luma16().enumerate_pixels() {
+            cells[x as usize][y as usize] = Some(pixel.0[0] as f64);
+        }
+        (0.0, u16::MAX as f64)
+    } else {
+        for (x, y, pixel) in image.to_luma8().enumerate_pixels() {
+            cells[x as usize][y as usize] = Some(pixel.0[0] as f64);
+        }
+        (0.0, u8::MAX as f64)
+    };
+    Ok(RawGrid { cells, range })
+}
+
+#[cfg(not(feature = "image"))]
+fn read_png(path: &Path) -> Result<RawGrid, ConfigError> {
+    Err(ConfigError::Heightmap {
+        path: path.to_path_buf(),
+        reason: "built without the image feature".to_string(),
+    })
+}
+
+/// Most cells an ASCII grid may have, to keep a bad header from asking for
+/// more memory than any height map needs.
+const MAX_CELLS: usize = 1 << 24;
+
+/// Parse `token` as a finite number, naming it `what` in the error.
+fn parse_finite(what: &str, token: &str) -> Result<f64, String> {
+    match token.parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        Ok(_) => Err(format!(
+            "bad {} '{}': expected a finite number",
+            what, token
+        )),
+        Err(e) => Err(format!("bad {} '{}': {}", what, token, e)),
+    }
+}
+
+/// Parse an ESRI ASCII grid: a header of `ncols`, `nrows`, corner
+/// coordinates, `cellsize` and an optional `nodata_value`, then the rows
+/// from north to south.
+fn read_ascii_grid(text: &str) -> Result<RawGrid, String> {
+    let mut tokens = text.split_whitespace().peekable();
+    let mut ncols = None;
+    let mut nrows = None;
+    let mut nodata = None;
+
+    // Header keys are the tokens starting with a letter
+    while let Some(key) = tokens.next_if(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+        let value = tokens
+            .next()
+            .ok_or_else(|| format!("missing value for {}", key))?;
+        let dimension = |value: &str| match value.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!(
+                "bad {} '{}': expected a whole number above 0",
+                key, value
+            )),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "ncols" => ncols = Some(dimension(value)?),
+            "nrows" => nrows = Some(dimension(value)?),
+            "nodata_value" => nodata = Some(parse_finite(key, value)?),
+            "xllcorner" | "yllcorner" | "xllcenter" | "yllcenter" | "cellsize" => {}
+            _ => return Err(format!("unknown header key {}", key)),
+        }
+    }
+    let ncols = ncols.ok_or("missing ncols")?;
+    let nrows = nrows.ok_or("missing nrows")?;
+    let count = ncols
+        .checked_mul(nrows)
+        .filter(|n| *n <= MAX_CELLS)
+        .ok_or_else(|| {
+            format!(
+                "{}x{} grid is over the {} cell limit",
+                ncols, nrows, MAX_CELLS
+            )
+        })?;
+
+    let values: Vec<&str> = tokens.collect();
+    if values.len() != count {
+        return Err(format!("expected {} cells, got {}", count, values.len()));
+    }
+
+    let mut cells = DynGrid::new(ncols, nrows, None);
+    let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+    for (i, token) in values.iter().enumerate() {
+        let v = parse_finite("cell", token)?;
+        if Some(v) != nodata {
+            cells[i % ncols][i / ncols] = Some(v);
+            range = (range.0.min(v), range.1.max(v));
+        }
+    }
+    if range.0 > range.1 {
+        return Err("every cell is nodata".to_string());
+    }
+
+    Ok(RawGrid { cells, range })
+}
@@ -0,0 +1,307 @@
+//! Surface water routing over a height map.
+//!
+//! Depressions are filled with the Priority-Flood+epsilon algorithm (Barnes,
+//! Lehman & Mulla, 2014), which leaves every cell with a strictly downhill
+//! path to an outlet.  Water is then routed D8 style to the steepest lower
+//! neighbor and accumulated downstream.
+
+use crate::terrain::DynGrid;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// Height added per cell when raising a depression or flat, so the filled
+/// surface always drains.
+const FLAT_EPSILON: f64 = 1e-9;
+
+/// Open set entry, ordered so `BinaryHeap` pops the lowest cell first.
+#[derive(PartialEq)]
+struct OpenCell {
+    height: f64,
+    tile: (usize, usize),
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .height
+            .total_cmp(&self.height)
+            .then_with(|| other.tile.cmp(&self.tile))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sea level leaving `land_fraction` of the tiles not in `masked` at or
+/// above it.
+///
+/// Tiles in `masked`, which shape masks and `ocean_border` keep under water,
+/// are left out of the count.  While there are any, sea level stays above
+/// the floor of the height range at -1 so they can sit below it.
+pub fn sea_level_for_land_fraction(
+    height: &DynGrid<f64>,
+    masked: &DynGrid<bool>,
+    land_fraction: f64,
+) -> f64 {
+    let mut heights = vec![];
+    let mut any_masked = false;
+    for x in 0..height.width() {
+        for y in 0..height.height() {
+            if masked[x][y] {
+                any_masked = true;
+            } else {
+                heights.push(height[x][y]);
+            }
+        }
+    }
+    let floor = if any_masked {
+        (-1.0f64).next_up()
+    } else {
+        -1.0
+    };
+    if heights.is_empty() {
+        return floor;
+    }
+    heights.sort_by(f64::total_cmp);
+
+    let land = (land_fraction * heights.len() as f64).round() as usize;
+    let ocean = heights.len() - land.min(heights.len());
+    match heights.get(ocean) {
+        Some(h) => h.max(floor),
+        // No land at all, the sea has to clear the highest peak
+        None => heights[heights.len() - 1].next_up(),
+    }
+}
+
+/// Raise every depression in `height` to its spill level.
+///
+/// Water leaves the map through `outlets` (typically the ocean) and over the
+/// map edges.  The returned surface is never below `height`, and away from
+/// outlets every cell has a strictly lower neighbor.
+pub fn fill_depressions(height: &DynGrid<f64>, outlets: &DynGrid<bool>) -> DynGrid<f64> {
+    let (width, depth) = (height.width(), height.height());
+    let mut filled = height.clone();
+    let mut closed = DynGrid::new(width, depth, false);
+    let mut open = BinaryHeap::new();
+    let mut pit = VecDeque::new();
+
+    for x in 0..width {
+        for y in 0..depth {
+            let edge = x == 0 || y == 0 || x + 1 == width || y + 1 == depth;
+            if edge || outlets[x][y] {
+                closed[x][y] = true;
+                open.push(OpenCell {
+                    height: height[x][y],
+                    tile: (x, y),
+                });
+            }
+        }
+    }
+
+    loop {
+        let tile = match pit.pop_front() {
+            Some(tile) => tile,
+            None => match open.pop() {
+                Some(cell) => cell.tile,
+                None => break,
+            },
+        };
+        let level = filled[tile.0][tile.1];
+
+        for n in height.get_neighbors(&tile) {
+            if closed[n.0][n.1] {
+                continue;
+            }
+            closed[n.0][n.1] = true;
+
+            if height[n.0][n.1] <= level + FLAT_EPSILON {
+                filled[n.0][n.1] = level + FLAT_EPSILON;
+                pit.push_back(n);
+            } else {
+                open.push(OpenCell {
+                    height: height[n.0][n.1],
+                    tile: n,
+                });
+            }
+        }
+    }
+
+    filled
+}
+
+/// D8 flow direction: the steepest strictly-lower neighbor of every cell, or
+/// `None` for outlets and cells water leaves the map from.
+pub fn flow_directions(
+    filled: &DynGrid<f64>,
+    outlets: &DynGrid<bool>,
+) -> DynGrid<Option<(usize, usize)>> {
+    let mut receivers = DynGrid::new(filled.width(), filled.height(), None);
+
+    for x in 0..filled.width() {
+        for y in 0..filled.height() {
+            if outlets[x][y] {
+                continue;
+            }
+
+            let mut steepest = 0.0;
+            for n in filled.get_neighbors(&(x, y)) {
+                let distance = if n.0 != x && n.1 != y {
+                    std::f64::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                let slope = (filled[x][y] - filled[n.0][n.1]) / distance;
+                if slope > steepest {
+                    steepest = slope;
+                    receivers[x][y] = Some(n);
+                }
+            }
+        }
+    }
+
+    receivers
+}
+
+/// Sum `runoff` down the flow directions, so each cell holds the total runoff
+/// of its upstream catchment (itself included).
+pub fn flow_accumulation(
+    filled: &DynGrid<f64>,
+    receivers: &DynGrid<Option<(usize, usize)>>,
+    runoff: &DynGrid<f64>,
+) -> DynGrid<f64> {
+    let mut accumulation = runoff.clone();
+
+    // Receivers are strictly lower than their donors, so visiting cells from
+    // the top down hands each cell its full upstream total before it passes
+    // it on.
+    let mut order: Vec<(usize, usize)> = (0..filled.width())
+        .flat_map(|x| (0..filled.height()).map(move |y| (x, y)))
+        .collect();
+    order.sort_unstable_by(|a, b| {
+        filled[b.0][b.1]
+            .total_cmp(&filled[a.0][a.1])
+            .then_with(|| a.cmp(b))
+    });
+
+    for tile in order {
+        if let Some(r) = receivers[tile.0][tile.1] {
+            accumulation[r.0][r.1] += accumulation[tile.0][tile.1];
+        }
+    }
+
+    accumulation
+}
+
+/// A body of standing water filling a depression up to its spill level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lake {
+    pub tiles: Vec<(usize, usize)>,
+    /// Height of the water surface, the level the depression spills at.
+    pub water_level: f64,
+    /// First tile outside the lake that its overflow runs into, `None` for
+    /// an endorheic lake that loses all its inflow to evaporation.
+    pub outflow: Option<(usize, usize)>,
+    /// Sum of water depth over the lake's tiles, in height units times tiles.
+    pub volume: f64,
+}
+
+impl Lake {
+    /// Surface area in tiles.
+    pub fn area(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn max_depth(&self, height: &DynGrid<f64>) -> f64 {
+        self.tiles
+            .iter()
+            .map(|t| self.water_level - height[t.0][t.1])
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Group the flooded cells of `filled` into lakes, overflowing wherever
+/// `receivers` lead out of them.
+///
+/// Returns the lakes along with a grid giving each tile's index into them.
+/// Depressions shallower than `min_depth` everywhere are left dry.
+pub fn find_lakes(
+    height: &DynGrid<f64>,
+    filled: &DynGrid<f64>,
+    receivers: &DynGrid<Option<(usize, usize)>>,
+    min_depth: f64,
+) -> (Vec<Lake>, DynGrid<Option<usize>>) {
+    let (width, depth) = (height.width(), height.height());
+    let mut lake_map = DynGrid::new(width, depth, None);
+    let mut visited = DynGrid::new(width, depth, false);
+    let mut lakes = vec![];
+
+    // Anything raised noticeably more than the flat epsilon is under water
+    let flooded = |x: usize, y: usize| filled[x][y] - height[x][y] > 1e3 * FLAT_EPSILON;
+
+    for x in 0..width {
+        for y in 0..depth {
+            if visited[x][y] || !flooded(x, y) {
+                continue;
+            }
+
+            // Flood fill the connected flooded region
+            let mut tiles = vec![(x, y)];
+            visited[x][y] = true;
+            let mut i = 0;
+            while i < tiles.len() {
+                for n in height.get_neighbors(&tiles[i]) {
+                    if !visited[n.0][n.1] && flooded(n.0, n.1) {
+                        visited[n.0][n.1] = true;
+                        tiles.push(n);
+                    }
+                }
+                i += 1;
+            }
+
+            // The filled surface only rises by epsilons away from the spill
+            // point, so its lowest value is the water level
+            let water_level = tiles
+                .iter()
+                .map(|t| filled[t.0][t.1])
+                .fold(f64::MAX, f64::min);
+            let max_depth = tiles
+                .iter()
+                .map(|t| water_level - height[t.0][t.1])
+                .fold(0.0, f64::max);
+            if max_depth < min_depth {
+                continue;
+            }
+
+            // Water leaves where a lake tile drains to a tile outside it.  A
+            // filled surface always has such a tile, but one that doesn't
+            // (say a hand-built grid) just holds its water as an endorheic lake
+            let index = lakes.len();
+            for t in &tiles {
+                lake_map[t.0][t.1] = Some(index);
+            }
+            let outflow = tiles
+                .iter()
+                .filter_map(|t| receivers[t.0][t.1])
+                .filter(|r| lake_map[r.0][r.1] != Some(index))
+                .min_by(|a, b| filled[a.0][a.1].total_cmp(&filled[b.0][b.1]).then(a.cmp(b)));
+            let volume = tiles
+                .iter()
+                .map(|t| (water_level - height[t.0][t.1]).max(0.0))
+                .sum();
+
+            lakes.push(Lake {
+                tiles,
+                water_level,
+                outflow,
+                volume,
+            });
+        }
+    }
+
+    (lakes, lake_map)
+}
@@ -0,0 +1,201 @@
+//! Köppen–Geiger climate classification.
+//!
+//! Follows the rules of Peel, Finlayson & McMahon, "Updated world map of the
+//! Köppen-Geiger climate classification" (2007), with a 0 °C boundary
+//! between temperate and continental climates.
+
+use serde::{Deserialize, Serialize};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Koppen {
+    /// Tropical rainforest
+    Af,
+    /// Tropical monsoon
+    Am,
+    /// Tropical savanna
+    Aw,
+    /// Hot desert
+    BWh,
+    /// Cold desert
+    BWk,
+    /// Hot semi-arid
+    BSh,
+    /// Cold semi-arid
+    BSk,
+    /// Hot-summer Mediterranean
+    Csa,
+    /// Warm-summer Mediterranean
+    Csb,
+    /// Cold-summer Mediterranean
+    Csc,
+    /// Monsoon-influenced humid subtropical
+    Cwa,
+    /// Subtropical highland
+    Cwb,
+    /// Cold subtropical highland
+    Cwc,
+    /// Humid subtropical
+    Cfa,
+    /// Oceanic
+    Cfb,
+    /// Subpolar oceanic
+    Cfc,
+    Dsa,
+    Dsb,
+    Dsc,
+    Dsd,
+    Dwa,
+    Dwb,
+    Dwc,
+    Dwd,
+    /// Hot-summer humid continental
+    Dfa,
+    /// Warm-summer humid continental
+    Dfb,
+    /// Subarctic
+    Dfc,
+    /// Extremely cold subarctic
+    Dfd,
+    /// Tundra
+    ET,
+    /// Ice cap
+    EF,
+}
+
+impl Koppen {
+    /// Colours of the usual Köppen map legend.
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            Koppen::Af => [0, 0, 255],
+            Koppen::Am => [0, 120, 255],
+            Koppen::Aw => [70, 170, 250],
+            Koppen::BWh => [255, 0, 0],
+            Koppen::BWk => [255, 150, 150],
+            Koppen::BSh => [245, 165, 0],
+            Koppen::BSk => [255, 220, 100],
+            Koppen::Csa => [255, 255, 0],
+            Koppen::Csb => [200, 200, 0],
+            Koppen::Csc => [150, 150, 0],
+            Koppen::Cwa => [150, 255, 150],
+            Koppen::Cwb => [100, 200, 100],
+            Koppen::Cwc => [50, 150, 50],
+            Koppen::Cfa => [200, 255, 80],
+            Koppen::Cfb => [100, 255, 80],
+            Koppen::Cfc => [50, 200, 0],
+            Koppen::Dsa => [255, 0, 255],
+            Koppen::Dsb => [200, 0, 200],
+            Koppen::Dsc => [150, 50, 150],
+            Koppen::Dsd => [150, 100, 150],
+            Koppen::Dwa => [170, 175, 255],
+            Koppen::Dwb => [90, 120, 220],
+            Koppen::Dwc => [75, 80, 180],
+            Koppen::Dwd => [50, 0, 135],
+            Koppen::Dfa => [0, 255, 255],
+            Koppen::Dfb => [55, 200, 255],
+            Koppen::Dfc => [0, 125, 125],
+            Koppen::Dfd => [0, 70, 95],
+            Koppen::ET => [178, 178, 178],
+            Koppen::EF => [102, 102, 102],
+        }
+    }
+
+    /// Classify a year of monthly mean temperatures (°C) and precipitation
+    /// totals (mm), January first.
+    pub fn classify(temperature: &[f64; 12], precipitation: &[f64; 12], northern: bool) -> Koppen {
+        let mean_temp = temperature.iter().sum::<f64>() / 12.0;
+        let hottest = temperature.iter().copied().fold(f64::MIN, f64::max);
+        let coldest = temperature.iter().copied().fold(f64::MAX, f64::min);
+        let total = precipitation.iter().sum::<f64>();
+        let driest = precipitation.iter().copied().fold(f64::MAX, f64::min);
+
+        if hottest < 10.0 {
+            return if hottest > 0.0 {
+                Koppen::ET
+            } else {
+                Koppen::EF
+            };
+        }
+
+        // April to September is summer in the north, winter in the south
+        let summer: Vec<f64> = (0..12)
+            .filter(|m| (3..9).contains(m) == northern)
+            .map(|m| precipitation[m])
+            .collect();
+        let winter: Vec<f64> = (0..12)
+            .filter(|m| (3..9).contains(m) != northern)
+            .map(|m| precipitation[m])
+            .collect();
+        let summer_total: f64 = summer.iter().sum();
+        let winter_total: f64 = winter.iter().sum();
+
+        // Aridity threshold, higher when the rain falls in the hot season
+        let threshold = if summer_total >= 0.7 * total {
+            20.0 * mean_temp + 280.0
+        } else if winter_total >= 0.7 * total {
+            20.0 * mean_temp
+        } else {
+            20.0 * mean_temp + 140.0
+        };
+        if total < threshold {
+            let hot = mean_temp >= 18.0;
+            return match (total < threshold / 2.0, hot) {
+                (true, true) => Koppen::BWh,
+                (true, false) => Koppen::BWk,
+                (false, true) => Koppen::BSh,
+                (false, false) => Koppen::BSk,
+            };
+        }
+
+        if coldest >= 18.0 {
+            return if driest >= 60.0 {
+                Koppen::Af
+            } else if driest >= 100.0 - total / 25.0 {
+                Koppen::Am
+            } else {
+                Koppen::Aw
+            };
+        }
+
+        let summer_driest = summer.iter().copied().fold(f64::MAX, f64::min);
+        let summer_wettest = summer.iter().copied().fold(0.0, f64::max);
+        let winter_driest = winter.iter().copied().fold(f64::MAX, f64::min);
+        let winter_wettest = winter.iter().copied().fold(0.0, f64::max);
+        // 0 dry summer, 1 dry winter, 2 no dry season
+        let season = if summer_driest < 40.0 && summer_driest < winter_wettest / 3.0 {
+            0
+        } else if winter_driest < summer_wettest / 10.0 {
+            1
+        } else {
+            2
+        };
+
+        let warm_months = temperature.iter().filter(|t| **t >= 10.0).count();
+        // 0 hot summer, 1 warm, 2 cold, 3 very cold winter
+        let summer_heat = if hottest >= 22.0 {
+            0
+        } else if warm_months >= 4 {
+            1
+        } else if coldest < -38.0 {
+            3
+        } else {
+            2
+        };
+
+        if coldest > 0.0 {
+            const TEMPERATE: [[Koppen; 3]; 3] = [
+                [Koppen::Csa, Koppen::Csb, Koppen::Csc],
+                [Koppen::Cwa, Koppen::Cwb, Koppen::Cwc],
+                [Koppen::Cfa, Koppen::Cfb, Koppen::Cfc],
+            ];
+            TEMPERATE[season][summer_heat.min(2)]
+        } else {
+            const CONTINENTAL: [[Koppen; 4]; 3] = [
+                [Koppen::Dsa, Koppen::Dsb, Koppen::Dsc, Koppen::Dsd],
+                [Koppen::Dwa, Koppen::Dwb, Koppen::Dwc, Koppen::Dwd],
+                [Koppen::Dfa, Koppen::Dfb, Koppen::Dfc, Koppen::Dfd],
+            ];
+            CONTINENTAL[season][summer_heat]
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Procedural world generation: height, climate and biome maps with rivers.
+//!
+//! The core generator has no terminal or image dependencies.  Rendering is
+//! behind cargo features:
+//!
+//! * `tui` - render a `Landmass` to the terminal with termion
+//! * `image` - write layers out as PNG images
+//! * `cli` - the `rustpcg` command line front-end (implies both of the above)
+//!
+//! ```
+//! use rustpcg::{Landmass, WorldConfig};
+//!
+//! let mut config = WorldConfig::default();
+//! config.width = 32;
+//! config.height = 16;
+//! config.autogen.seed = Some(42);
+//!
+//! let mut land = Landmass::new(config.width, config.height);
+//! land.autogen(&config.autogen);
+//! let height = land.height_map[3][5];
+//! assert!((-1.0..=1.0).contains(&height));
+//! ```
+
+pub mod bathymetry;
+pub mod biomes;
+pub mod climate;
+pub mod config;
+pub mod currents;
+pub mod erosion;
+pub mod heightmap;
+pub mod hydrology;
+pub mod koppen;
+pub mod noise_graph;
+pub mod plates;
+#[cfg(feature = "tui")]
+pub mod render;
+#[cfg(feature = "image")]
+pub mod render_image;
+pub mod rivers;
+pub mod shapes;
+pub mod terrain;
+pub mod watersheds;
+
+pub use crate::bathymetry::{Bathymetry, Seafloor};
+pub use crate::biomes::{BiomeRule, BiomeTable, Landforms, PrecipBound, TileConditions};
+pub use crate::climate::{ClimateUnits, Continentality, MoistureModel, Planet, Seasons};
+pub use crate::config::{ConfigError, Format, Preset, WorldConfig};
+pub use crate::currents::OceanCurrents;
+pub use crate::erosion::{HydraulicErosion, ThermalErosion};
+pub use crate::heightmap::HeightmapFile;
+pub use crate::hydrology::Lake;
+pub use crate::koppen::Koppen;
+pub use crate::noise_graph::{Fractal, NoiseNode, WorleyOutput};
+pub use crate::plates::{Crust, Plate, TectonicPlates};
+pub use crate::rivers::{RiverNetwork, RiverSegment};
+pub use crate::shapes::ShapeMask;
+pub use crate::terrain::{AutoGenConfig, Biome, DynGrid, Feature, Grid, Landmass};
+pub use crate::watersheds::{Basin, Outlet, Watersheds};
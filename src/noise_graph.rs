@@ -0,0 +1,273 @@
+//! Noise graphs: `noise` crate modules wired together from config.
+//!
+//! A layer's noise is a tree of `NoiseNode`s, written in config as tables
+//! with a `type` key.  Leaves are noise sources, inner nodes combine or
+//! reshape the nodes below them:
+//!
+//! ```toml
+//! [autogen.height_noise]
+//! type = "add"
+//!
+//! [[autogen.height_noise.sources]]
+//! type = "ridged_multi"
+//! frequency = 3.0
+//!
+//! [[autogen.height_noise.sources]]
+//! type = "billow"
+//! frequency = 8.0
+//! persistence = 0.3
+//! ```
+
+use noise::core::worley::ReturnType;
+use noise::{
+    Add, Billow, Constant, Fbm, MultiFractal, Multiply, NoiseFn, Perlin, RidgedMulti, ScaleBias,
+    ScalePoint, Seedable, Select, Simplex, Terrace, Turbulence, Worley,
+};
+use serde::{Deserialize, Serialize};
+
+/// Most octaves the `noise` crate's fractals sum, it ignores any past this.
+pub const MAX_OCTAVES: usize = Fbm::<Simplex>::MAX_OCTAVES;
+
+/// Octave settings shared by the fractal sources.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fractal {
+    pub frequency: f64,
+    pub octaves: usize,
+    /// Frequency multiplier from one octave to the next.
+    pub lacunarity: f64,
+    /// Amplitude multiplier from one octave to the next.
+    pub persistence: f64,
+}
+
+impl Fractal {
+    pub fn with_frequency(frequency: f64) -> Fractal {
+        Fractal {
+            frequency,
+            ..Fractal::default()
+        }
+    }
+}
+
+impl Default for Fractal {
+    /// The `noise` crate's `Fbm` defaults.
+    fn default() -> Fractal {
+        Fractal {
+            frequency: Fbm::<Simplex>::DEFAULT_FREQUENCY,
+            octaves: Fbm::<Simplex>::DEFAULT_OCTAVE_COUNT,
+            lacunarity: Fbm::<Simplex>::DEFAULT_LACUNARITY,
+            persistence: Fbm::<Simplex>::DEFAULT_PERSISTENCE,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorleyOutput {
+    /// Distance to the nearest cell point, for cracks and cell walls.
+    Distance,
+    /// A constant value per cell, for flat patches.
+    #[default]
+    Value,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+fn default_roughness() -> usize {
+    3
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NoiseNode {
+    /// Fractal Brownian motion over simplex noise, the classic smooth hills.
+    Fbm(Fractal),
+    /// Sharp ridges, for mountain chains.
+    RidgedMulti(Fractal),
+    /// Rounded, puffy lumps.
+    Billow(Fractal),
+    Perlin {
+        #[serde(default = "one")]
+        frequency: f64,
+    },
+    /// Cellular noise.
+    Worley {
+        #[serde(default = "one")]
+        frequency: f64,
+        #[serde(default)]
+        output: WorleyOutput,
+    },
+    Constant {
+        value: f64,
+    },
+    /// Sum of all sources.
+    Add {
+        sources: Vec<NoiseNode>,
+    },
+    /// Product of all sources.
+    Multiply {
+        sources: Vec<NoiseNode>,
+    },
+    /// `inside` where `control` is within the bounds, `outside` elsewhere,
+    /// blending over `falloff` at the edges.
+    Select {
+        outside: Box<NoiseNode>,
+        inside: Box<NoiseNode>,
+        control: Box<NoiseNode>,
+        lower_bound: f64,
+        upper_bound: f64,
+        #[serde(default)]
+        falloff: f64,
+    },
+    /// Flatten `source` into steps at the control points.
+    Terrace {
+        source: Box<NoiseNode>,
+        control_points: Vec<f64>,
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Domain warping: displace the point `source` is sampled at by up to
+    /// `power` along fractal noise of the given frequency and roughness.
+    Warp {
+        source: Box<NoiseNode>,
+        #[serde(default = "one")]
+        frequency: f64,
+        #[serde(default = "one")]
+        power: f64,
+        #[serde(default = "default_roughness")]
+        roughness: usize,
+    },
+    /// `source * scale + bias`.
+    ScaleBias {
+        source: Box<NoiseNode>,
+        #[serde(default = "one")]
+        scale: f64,
+        #[serde(default)]
+        bias: f64,
+    },
+}
+
+/// Hand out `seed` and move on to the next one.
+fn next_seed(seed: &mut u32) -> u32 {
+    let s = *seed;
+    *seed = seed.wrapping_add(1);
+    s
+}
+
+/// A built noise graph, sampled at map coordinates.
+pub type NoiseGraph = Box<dyn NoiseFn<f64, 2>>;
+
+impl NoiseNode {
+    /// Instantiate the graph.  Every source gets its own seed counting up
+    /// from `seed` in depth first order, so repeated sources differ.
+    pub fn build(&self, seed: u32) -> NoiseGraph {
+        let mut seed = seed;
+        self.build_from(&mut seed)
+    }
+
+    fn build_from(&self, seed: &mut u32) -> NoiseGraph {
+        match self {
+            NoiseNode::Fbm(f) => Box::new(
+                Fbm::<Simplex>::new(next_seed(seed))
+                    .set_frequency(f.frequency)
+                    .set_octaves(f.octaves)
+                    .set_lacunarity(f.lacunarity)
+                    .set_persistence(f.persistence),
+            ),
+            NoiseNode::RidgedMulti(f) => Box::new(
+                RidgedMulti::<Perlin>::new(next_seed(seed))
+                    .set_frequency(f.frequency)
+                    .set_octaves(f.octaves)
+                    .set_lacunarity(f.lacunarity)
+                    .set_persistence(f.persistence),
+            ),
+            NoiseNode::Billow(f) => Box::new(
+                Billow::<Perlin>::new(next_seed(seed))
+                    .set_frequency(f.frequency)
+                    .set_octaves(f.octaves)
+                    .set_lacunarity(f.lacunarity)
+                    .set_persistence(f.persistence),
+            ),
+            NoiseNode::Perlin { frequency } => {
+                Box::new(ScalePoint::new(Perlin::new(next_seed(seed))).set_scale(*frequency))
+            }
+            NoiseNode::Worley { frequency, output } => {
+                let return_type = match output {
+                    WorleyOutput::Distance => ReturnType::Distance,
+                    WorleyOutput::Value => ReturnType::Value,
+                };
+                Box::new(
+                    Worley::new(next_seed(seed))
+                        .set_frequency(*frequency)
+                        .set_return_type(return_type),
+                )
+            }
+            NoiseNode::Constant { value } => Box::new(Constant::new(*value)),
+            NoiseNode::Add { sources } => sources
+                .iter()
+                .map(|s| s.build_from(seed))
+                .reduce(|a, b| Box::new(Add::new(a, b)))
+                .unwrap_or_else(|| Box::new(Constant::new(0.0))),
+            NoiseNode::Multiply { sources } => sources
+                .iter()
+                .map(|s| s.build_from(seed))
+                .reduce(|a, b| Box::new(Multiply::new(a, b)))
+                .unwrap_or_else(|| Box::new(Constant::new(1.0))),
+            NoiseNode::Select {
+                outside,
+                inside,
+                control,
+                lower_bound,
+                upper_bound,
+                falloff,
+            } => {
+                let outside = outside.build_from(seed);
+                let inside = inside.build_from(seed);
+                let control = control.build_from(seed);
+                Box::new(
+                    Select::new(outside, inside, control)
+                        .set_bounds(*lower_bound, *upper_bound)
+                        .set_falloff(*falloff),
+                )
+            }
+            NoiseNode::Terrace {
+                source,
+                control_points,
+                invert,
+            } => {
+                let terrace = control_points
+                    .iter()
+                    .fold(Terrace::new(source.build_from(seed)), |t, p| {
+                        t.add_control_point(*p)
+                    });
+                Box::new(terrace.invert_terraces(*invert))
+            }
+            NoiseNode::Warp {
+                source,
+                frequency,
+                power,
+                roughness,
+            } => {
+                let source = source.build_from(seed);
+                Box::new(
+                    Turbulence::<_, Perlin>::new(source)
+                        .set_seed(next_seed(seed))
+                        .set_frequency(*frequency)
+                        .set_power(*power)
+                        .set_roughness(*roughness),
+                )
+            }
+            NoiseNode::ScaleBias {
+                source,
+                scale,
+                bias,
+            } => Box::new(
+                ScaleBias::new(source.build_from(seed))
+                    .set_scale(*scale)
+                    .set_bias(*bias),
+            ),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Plate tectonics for continent-scale structure.
+//!
+//! The map is split into plates by a warped Voronoi diagram.  Each plate is
+//! continental or oceanic crust drifting in its own direction, and where two
+//! plates meet their relative motion decides the relief: collisions push up
+//! mountain ranges (or a trench where ocean dives under a continent), and
+//! plates pulling apart open rift valleys on land and ridges under the sea.
+
+use crate::terrain::DynGrid;
+use noise::{Fbm, MultiFractal, NoiseFn, Simplex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Plate generation parameters.  Heights are in height map units, distances
+/// in tiles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TectonicPlates {
+    pub plate_count: usize,
+    /// Share of the plates carrying continental crust.
+    pub continental_fraction: f64,
+    pub continental_height: f64,
+    pub oceanic_height: f64,
+    /// Uplift where plates collide head on.
+    pub mountain_height: f64,
+    /// Depth of rifts where continental plates pull apart.
+    pub rift_depth: f64,
+    /// Depth of trenches where oceanic crust dives under a continent.
+    pub trench_depth: f64,
+    /// Distance from a boundary over which its relief fades out.
+    pub boundary_width: f64,
+    /// How far plate boundaries wander from straight Voronoi edges.
+    pub boundary_noise: f64,
+    /// Weight of the plate relief against the noise height map, from 0
+    /// (noise only) to 1 (plates only).
+    pub blend: f64,
+}
+
+impl Default for TectonicPlates {
+    fn default() -> TectonicPlates {
+        TectonicPlates {
+            plate_count: 12,
+            continental_fraction: 0.35,
+            continental_height: 0.25,
+            oceanic_height: -0.45,
+            mountain_height: 0.8,
+            rift_depth: 0.25,
+            trench_depth: 0.4,
+            boundary_width: 6.0,
+            boundary_noise: 8.0,
+            blend: 0.6,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Crust {
+    Continental,
+    Oceanic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plate {
+    pub center: (f64, f64),
+    pub crust: Crust,
+    /// Drift in tiles per unit time.
+    pub velocity: (f64, f64),
+}
+
+/// Nearest boundary as seen from inside a plate.
+#[derive(Copy, Clone)]
+struct Boundary {
+    /// How fast the plates close on each other, negative when separating.
+    convergence: f64,
+    /// Crust on the far side.
+    other: Crust,
+    distance: usize,
+}
+
+/// Seed the plates and assign every tile to one.
+///
+/// Returns the plates along with a grid giving each tile's index into them.
+pub fn generate_plates<R: Rng>(
+    width: usize,
+    height: usize,
+    params: &TectonicPlates,
+    noise_seed: u32,
+    rng: &mut R,
+) -> (Vec<Plate>, DynGrid<usize>) {
+    let count = params.plate_count.max(1);
+    let continental = (count as f64 * params.continental_fraction).round() as usize;
+    let mut crusts: Vec<Crust> = (0..count)
+        .map(|i| {
+            if i < continental {
+                Crust::Continental
+            } else {
+                Crust::Oceanic
+            }
+        })
+        .collect();
+    crusts.shuffle(rng);
+
+    let plates: Vec<Plate> = crusts
+        .into_iter()
+        .map(|crust| {
+            let angle = rng.gen_range(0.0..std::f64::consts::TAU);
+            let speed = rng.gen_range(0.2..1.0);
+            Plate {
+                center: (
+                    rng.gen_range(0.0..width as f64),
+                    rng.gen_range(0.0..height as f64),
+                ),
+                crust,
+                velocity: (speed * angle.cos(), speed * angle.sin()),
+            }
+        })
+        .collect();
+
+    // Warp the tile positions before finding the nearest center, so
+    // boundaries meander instead of running dead straight
+    let warp_x = Fbm::<Simplex>::new(noise_seed).set_frequency(4.0);
+    let warp_y = Fbm::<Simplex>::new(noise_seed.wrapping_add(1)).set_frequency(4.0);
+    let scale = width.max(height) as f64;
+
+    let mut plate_map = DynGrid::new(width, height, 0);
+    for x in 0..width {
+        for y in 0..height {
+            let p = [x as f64 / scale, y as f64 / scale];
+            let wx = x as f64 + params.boundary_noise * warp_x.get(p);
+            let wy = y as f64 + params.boundary_noise * warp_y.get(p);
+            let distance =
+                |plate: &Plate| (plate.center.0 - wx).powi(2) + (plate.center.1 - wy).powi(2);
+            plate_map[x][y] = (0..plates.len())
+                .min_by(|a, b| distance(&plates[*a]).total_cmp(&distance(&plates[*b])))
+                .unwrap();
+        }
+    }
+
+    (plates, plate_map)
+}
+
+/// Height the plates give every tile: the crust's base height, smoothed
+/// across plate edges into shelves, plus relief near boundaries.
+pub fn plate_relief(
+    plates: &[Plate],
+    plate_map: &DynGrid<usize>,
+    params: &TectonicPlates,
+) -> DynGrid<f64> {
+    let (width, height) = (plate_map.width(), plate_map.height());
+
+    // Find boundary tiles and how hard their plates push against their
+    // neighbors'
+    let mut nearest: DynGrid<Option<Boundary>> = DynGrid::new(width, height, None);
+    let mut queue = VecDeque::new();
+    for x in 0..width {
+        for y in 0..height {
+            let own = &plates[plate_map[x][y]];
+            let mut total = 0.0;
+            let mut count = 0;
+            let mut other = Crust::Oceanic;
+            for n in plate_map.get_neighbors(&(x, y)) {
+                if plate_map[n.0][n.1] == plate_map[x][y] {
+                    continue;
+                }
+                let theirs = &plates[plate_map[n.0][n.1]];
+                // Closing speed along the direction to the neighbor
+                let (dx, dy) = (n.0 as f64 - x as f64, n.1 as f64 - y as f64);
+                let len = (dx * dx + dy * dy).sqrt();
+                let relative = (
+                    own.velocity.0 - theirs.velocity.0,
+                    own.velocity.1 - theirs.velocity.1,
+                );
+                total += (relative.0 * dx + relative.1 * dy) / len;
+                count += 1;
+                if theirs.crust == Crust::Continental {
+                    other = Crust::Continental;
+                }
+            }
+            if count > 0 {
+                // Relative speeds reach 2, scale them back to [-1, 1]
+                nearest[x][y] = Some(Boundary {
+                    convergence: total / count as f64 / 2.0,
+                    other,
+                    distance: 0,
+                });
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    // Spread each boundary inwards through its own plate
+    let reach = params.boundary_width.ceil() as usize;
+    while let Some(tile) = queue.pop_front() {
+        let boundary = nearest[tile.0][tile.1].unwrap();
+        if boundary.distance >= reach {
+            continue;
+        }
+        for n in plate_map.get_neighbors(&tile) {
+            if nearest[n.0][n.1].is_none() && plate_map[n.0][n.1] == plate_map[tile.0][tile.1] {
+                nearest[n.0][n.1] = Some(Boundary {
+                    distance: boundary.distance + 1,
+                    ..boundary
+                });
+                queue.push_back(n);
+            }
+        }
+    }
+
+    // Base crust height, blurred so continents slope down into the ocean
+    let mut base = DynGrid::new(width, height, 0.0);
+    for x in 0..width {
+        for y in 0..height {
+            base[x][y] = match plates[plate_map[x][y]].crust {
+                Crust::Continental => params.continental_height,
+                Crust::Oceanic => params.oceanic_height,
+            };
+        }
+    }
+    for _ in 0..reach {
+        let mut blurred = base.clone();
+        for x in 0..width {
+            for y in 0..height {
+                let neighbors = base.get_neighbors(&(x, y));
+                let sum: f64 = neighbors.iter().map(|n| base[n.0][n.1]).sum();
+                blurred[x][y] = (sum + base[x][y]) / (neighbors.len() + 1) as f64;
+            }
+        }
+        base = blurred;
+    }
+
+    let mut relief = base;
+    for x in 0..width {
+        for y in 0..height {
+            let boundary = match nearest[x][y] {
+                Some(b) => b,
+                None => continue,
+            };
+            let crust = plates[plate_map[x][y]].crust;
+            let falloff = |width: f64| (1.0 - boundary.distance as f64 / width).max(0.0).powi(2);
+            let strength = boundary.convergence.abs();
+
+            relief[x][y] += if boundary.convergence > 0.0 {
+                match (crust, boundary.other) {
+                    // Subduction: a narrow trench on the diving plate
+                    (Crust::Oceanic, Crust::Continental) => {
+                        -params.trench_depth * strength * falloff(params.boundary_width / 2.0)
+                    }
+                    // Island arcs rise less than continental ranges
+                    (Crust::Oceanic, Crust::Oceanic) => {
+                        0.5 * params.mountain_height * strength * falloff(params.boundary_width)
+                    }
+                    (Crust::Continental, _) => {
+                        params.mountain_height * strength * falloff(params.boundary_width)
+                    }
+                }
+            } else {
+                match crust {
+                    Crust::Continental => {
+                        -params.rift_depth * strength * falloff(params.boundary_width)
+                    }
+                    // Mid-ocean ridge
+                    Crust::Oceanic => {
+                        0.5 * params.rift_depth * strength * falloff(params.boundary_width)
+                    }
+                }
+            };
+        }
+    }
+
+    relief
+}
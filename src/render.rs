@@ -1,4 +1,5 @@
-use crate::terrain::{Biome, Feature, Landmass};
+use crate::bathymetry::Seafloor;
+use crate::terrain::{Biome, DynGrid, Feature, Landmass};
 use std::collections::HashSet;
 use std::io::{stdin, stdout, Write};
 use termion::color::{Bg, Fg, Rgb};
@@ -8,21 +9,32 @@ use termion::*;
 
 impl Biome {
     fn get_color(&self) -> Rgb {
-        match self {
-            Biome::Tundra => Rgb(147, 168, 173),
-            Biome::BorealForest => Rgb(0, 80, 70),
-            Biome::TemperateRainforest => Rgb(25, 55, 0),
-            Biome::TemperateSeasonalForest => Rgb(145, 215, 70),
-            Biome::Shrubland => Rgb(130, 150, 100),
-            Biome::ColdDesert => Rgb(210, 190, 140),
-            Biome::TropicalRainforest => Rgb(48, 127, 55),
-            Biome::Savanna => Rgb(202, 139, 43),
-            Biome::SubtropicalDesert => Rgb(245, 200, 80),
+        let [r, g, b] = self.rgb();
+        Rgb(r, g, b)
+    }
+}
+
+/// Grey level of every tile of a grid in real units (°C, cm), stretched
+/// from its lowest to its highest value.
+fn grey_levels(grid: &DynGrid<f64>) -> DynGrid<u8> {
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for x in 0..grid.width() {
+        for y in 0..grid.height() {
+            min = min.min(grid[x][y]);
+            max = max.max(grid[x][y]);
         }
     }
+    let scale = if max > min { 255.0 / (max - min) } else { 0.0 };
+    let mut levels = DynGrid::new(grid.width(), grid.height(), 0);
+    for x in 0..grid.width() {
+        for y in 0..grid.height() {
+            levels[x][y] = ((grid[x][y] - min) * scale) as u8;
+        }
+    }
+    levels
 }
 
-impl<const X: usize, const Y: usize> Landmass<X, Y> {
+impl Landmass {
     pub fn tui_render(&self) {
         let stdin = stdin();
         let mut stdout = stdout()
@@ -32,9 +44,10 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
 
         let offset: u8 = 0;
         let mut used_biome_set = HashSet::<Biome>::new();
+        let mut used_seafloor_set = HashSet::<Seafloor>::new();
 
-        for x in 0..X {
-            for y in 0..Y {
+        for x in 0..self.width() {
+            for y in 0..self.height() {
                 let value = (127.0 * (self.height_map[x][y] + 1.0)) as u8;
 
                 let (tile_color, tile_color_bg, tile_char) = {
@@ -52,12 +65,33 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
                                 let tchar = '~';
                                 (tc, tcb, tchar)
                             }
-                            Feature::Ocean => {
-                                let tc = color::Fg(color::Rgb(0, 0, value.saturating_add(offset)));
-                                let tcb = color::Bg(color::Rgb(0, 0, value.saturating_add(offset)));
+                            Feature::Lake => {
+                                let tc = color::Fg(color::Rgb(90, 160, 255));
+                                let tcb =
+                                    color::Bg(color::Rgb(0, 40, value.saturating_add(offset)));
                                 let tchar = '~';
                                 (tc, tcb, tchar)
                             }
+                            Feature::Ocean => match self.seafloor_map[x][y] {
+                                Some(floor) => {
+                                    used_seafloor_set.insert(floor);
+                                    let [r, g, b] = floor.rgb();
+                                    let tc = Fg(Rgb(
+                                        r.saturating_add(40),
+                                        g.saturating_add(40),
+                                        b.saturating_add(40),
+                                    ));
+                                    (tc, Bg(Rgb(r, g, b)), floor.tile_char())
+                                }
+                                None => {
+                                    // Shade by depth, so the shallows look the
+                                    // same whatever the sea level
+                                    let depth = self.sea_level - self.height_map[x][y];
+                                    let value = (127.0 * (1.0 - depth)).max(0.0) as u8;
+                                    let value = value.saturating_add(offset);
+                                    (Fg(Rgb(0, 0, value)), Bg(Rgb(0, 0, value)), '~')
+                                }
+                            },
                         }
                     } else {
                         let biome = self.biome_map[x][y];
@@ -81,19 +115,19 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         writeln!(
             stdout,
             "{}{}{}Terrain map",
-            cursor::Goto(1, (Y + 1) as u16),
+            cursor::Goto(1, (self.height() + 1) as u16),
             color::Fg(color::Reset),
             color::Bg(color::Reset),
         )
         .expect("Failed to write!()");
 
-        // Print out biome colors
-        let mut cntr = 2;
-        for b in used_biome_set {
+        // Print out biome colors, then the ocean floor's
+        let biome_count = used_biome_set.len();
+        for (cntr, b) in used_biome_set.into_iter().enumerate() {
             writeln!(
                 stdout,
                 "{}{}{}##{}{} {:?}\t",
-                cursor::Goto(1, (Y + cntr) as u16),
+                cursor::Goto(1, (self.height() + 2 + cntr) as u16),
                 Fg(b.get_color()),
                 Bg(b.get_color()),
                 Fg(color::Reset),
@@ -101,13 +135,24 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
                 b,
             )
             .expect("Failed to write!()");
-            cntr += 1;
+        }
+        for (cntr, f) in used_seafloor_set.into_iter().enumerate() {
+            let [r, g, b] = f.rgb();
+            writeln!(
+                stdout,
+                "{}{}{}##{}{} {:?}\t",
+                cursor::Goto(1, (self.height() + 2 + biome_count + cntr) as u16),
+                Fg(Rgb(r, g, b)),
+                Bg(Rgb(r, g, b)),
+                Fg(color::Reset),
+                Bg(color::Reset),
+                f,
+            )
+            .expect("Failed to write!()");
         }
 
         stdout.flush().expect("Failed to flush stdout");
-        for _k in stdin.keys() {
-            break;
-        }
+        let _ = stdin.keys().next();
         println!("{}{}{}\n\r", style::Reset, clear::All, cursor::Show);
     }
 
@@ -117,9 +162,10 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
             .into_raw_mode()
             .expect("Failed to enter raw mode for termion.");
         writeln!(stdout, "{}{}", clear::All, cursor::Hide).expect("Failed to writeln!()");
-        for x in 0..X {
-            for y in 0..Y {
-                let value = (127.0 * (self.precip_map[x][y] + 1.0)) as u8;
+        let levels = grey_levels(&self.precip_map);
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                let value = levels[x][y];
                 let tile_color = color::Fg(color::Rgb(value, value, value));
                 let tile_color_bg = color::Bg(color::Rgb(value, value, value));
                 let tile_char = '#';
@@ -137,15 +183,13 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         writeln!(
             stdout,
             "{}{}{}Precipitation map",
-            cursor::Goto(1, (Y + 1) as u16),
+            cursor::Goto(1, (self.height() + 1) as u16),
             color::Fg(color::Reset),
             color::Bg(color::Reset),
         )
         .expect("Failed to write!()");
         stdout.flush().expect("Failed to flush stdout");
-        for _k in stdin.keys() {
-            break;
-        }
+        let _ = stdin.keys().next();
         println!("{}{}{}\n\r", style::Reset, clear::All, cursor::Show);
     }
 
@@ -155,9 +199,10 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
             .into_raw_mode()
             .expect("Failed to enter raw mode for termion.");
         writeln!(stdout, "{}{}", clear::All, cursor::Hide).expect("Failed to writeln!()");
-        for x in 0..X {
-            for y in 0..Y {
-                let value = (127.0 * (self.temperature_map[x][y] + 1.0)) as u8;
+        let levels = grey_levels(&self.temperature_map);
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                let value = levels[x][y];
                 let tile_color = color::Fg(color::Rgb(value, value, value));
                 let tile_color_bg = color::Bg(color::Rgb(value, value, value));
                 let tile_char = '#';
@@ -175,15 +220,13 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         writeln!(
             stdout,
             "{}{}{}Temperature map",
-            cursor::Goto(1, (Y + 1) as u16),
+            cursor::Goto(1, (self.height() + 1) as u16),
             color::Fg(color::Reset),
             color::Bg(color::Reset),
         )
         .expect("Failed to write!()");
         stdout.flush().expect("Failed to flush stdout");
-        for _k in stdin.keys() {
-            break;
-        }
+        let _ = stdin.keys().next();
         println!("{}{}{}\n\r", style::Reset, clear::All, cursor::Show);
     }
 
@@ -212,17 +255,37 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
                 tile_color = Fg(Rgb(70, 100, 40));
                 tile_char = 'p';
             }
+            Biome::IceSheet => {
+                tile_color = Fg(Rgb(170, 200, 230));
+                tile_char = '*';
+            }
+            Biome::Steppe => {
+                tile_color = Fg(Rgb(140, 135, 70));
+                tile_char = '"';
+            }
+            Biome::Alpine => {
+                tile_color = Fg(Rgb(255, 255, 255));
+                tile_char = '^';
+            }
+            Biome::Montane => {
+                tile_color = Fg(Rgb(90, 70, 70));
+                tile_char = 'n';
+            }
+            Biome::Wetland => {
+                tile_color = Fg(Rgb(90, 160, 200));
+                tile_char = ',';
+            }
+            Biome::Beach => {
+                tile_color = Fg(Rgb(190, 170, 110));
+                tile_char = '.';
+            }
+            Biome::Mangrove => {
+                tile_color = Fg(Rgb(0, 50, 30));
+                tile_char = '%';
+            }
             _ => {}
         }
 
-        if h > 0.8 {
-            tile_color = Fg(Rgb(255, 255, 255));
-            tile_char = '^';
-        } else if h > 0.7 {
-            tile_color = Fg(Rgb(90, 70, 70));
-            tile_char = '^';
-        }
-
         (tile_color, tile_color_bg, tile_char)
     }
 
@@ -234,8 +297,8 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         writeln!(stdout, "{}{}", clear::All, cursor::Hide).expect("Failed to writeln!()");
 
         let mut used_biome_set = HashSet::<Biome>::new();
-        for x in 0..X {
-            for y in 0..Y {
+        for x in 0..self.width() {
+            for y in 0..self.height() {
                 let biome = self.biome_map[x][y];
                 let (tile_color, tile_color_bg, tile_char) = self.get_biome_tile(biome, x, y);
 
@@ -254,19 +317,18 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         writeln!(
             stdout,
             "{}{}{}Biome map",
-            cursor::Goto(1, (Y + 1) as u16),
+            cursor::Goto(1, (self.height() + 1) as u16),
             Fg(color::Reset),
             Bg(color::Reset),
         )
         .expect("Failed to write!()");
 
         // Print out biome colors
-        let mut cntr = 2;
-        for b in used_biome_set {
+        for (cntr, b) in used_biome_set.into_iter().enumerate() {
             writeln!(
                 stdout,
                 "{}{}{}##{}{} {:?}\t",
-                cursor::Goto(1, (Y + cntr) as u16),
+                cursor::Goto(1, (self.height() + 2 + cntr) as u16),
                 Fg(b.get_color()),
                 Bg(b.get_color()),
                 Fg(color::Reset),
@@ -274,13 +336,10 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
                 b,
             )
             .expect("Failed to write!()");
-            cntr += 1;
         }
 
         stdout.flush().expect("Failed to flush stdout");
-        for _k in stdin.keys() {
-            break;
-        }
+        let _ = stdin.keys().next();
         println!("{}{}{}\n\r", style::Reset, clear::All, cursor::Show);
     }
 }
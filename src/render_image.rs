@@ -1,8 +1,6 @@
-use image;
+use image::{ImageBuffer, ImageResult, Rgb};
 
-use image::{ImageBuffer, Rgb};
-
-pub fn render_greyscale(filename: &str, map: &[Vec<u8>]) {
+pub fn render_greyscale(filename: &str, map: &[Vec<u8>]) -> ImageResult<()> {
     // a default (black) image containing Rgb values
     let width = map.len() as u32;
     let height = map[0].len() as u32;
@@ -15,5 +13,17 @@ pub fn render_greyscale(filename: &str, map: &[Vec<u8>]) {
     }
 
     // write it out to a file
-    image.save(filename).expect("failed to save output image");
+    image.save(filename)
+}
+
+pub fn render_rgb(filename: &str, map: &[Vec<[u8; 3]>]) -> ImageResult<()> {
+    let width = map.len() as u32;
+    let height = map[0].len() as u32;
+    let mut image = ImageBuffer::new(width, height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        *pixel = Rgb(map[x as usize][y as usize]);
+    }
+
+    image.save(filename)
 }
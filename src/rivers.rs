@@ -0,0 +1,237 @@
+//! River network graph built from the river tiles of a `Landmass`.
+//!
+//! Rivers are split into segments at sources, confluences and lakes.  Each
+//! segment links to the one it flows into, so the network can be walked
+//! downstream to a mouth or upstream into its tributaries.
+
+use crate::terrain::{DynGrid, Feature};
+use std::collections::{HashMap, VecDeque};
+
+/// A stretch of river between sources, confluences, lakes and mouths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RiverSegment {
+    /// Tiles in flow order, upstream first.
+    pub tiles: Vec<(usize, usize)>,
+    /// Segment this one flows into, `None` at a mouth.
+    pub downstream: Option<usize>,
+    /// Segments flowing into this one.
+    pub upstream: Vec<usize>,
+    /// Lake this segment empties into before reaching `downstream`.
+    pub lake: Option<usize>,
+    pub strahler: u32,
+    pub shreve: u32,
+    /// Length in tiles, diagonal steps counting as sqrt(2), up to the start
+    /// of the downstream segment.
+    pub length: f64,
+    /// Flow at the downstream end: the runoff of the whole upstream
+    /// catchment, in tiles of full rainfall.
+    pub discharge: f64,
+}
+
+impl RiverSegment {
+    pub fn source(&self) -> (usize, usize) {
+        self.tiles[0]
+    }
+
+    pub fn end(&self) -> (usize, usize) {
+        self.tiles[self.tiles.len() - 1]
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RiverNetwork {
+    pub segments: Vec<RiverSegment>,
+    /// Index into `segments` of the segment covering each river tile.
+    segment_map: HashMap<(usize, usize), usize>,
+}
+
+fn step_length(a: (usize, usize), b: (usize, usize)) -> f64 {
+    if a.0 != b.0 && a.1 != b.1 {
+        std::f64::consts::SQRT_2
+    } else {
+        1.0
+    }
+}
+
+impl RiverNetwork {
+    /// Build the network over all `River` and `RiverSource` tiles, linking
+    /// rivers that run into a lake to the river leaving it.
+    pub fn build(
+        features: &HashMap<(usize, usize), Feature>,
+        flow_direction_map: &DynGrid<Option<(usize, usize)>>,
+        flow_accumulation_map: &DynGrid<f64>,
+        lake_map: &DynGrid<Option<usize>>,
+    ) -> RiverNetwork {
+        let is_river = |t: &(usize, usize)| {
+            matches!(
+                features.get(t),
+                Some(Feature::River) | Some(Feature::RiverSource)
+            )
+        };
+
+        let mut tiles: Vec<(usize, usize)> = features.keys().copied().filter(is_river).collect();
+        tiles.sort_unstable();
+
+        // Next river tile downstream of each river tile, following the flow
+        // straight through any lake in between
+        let mut next = HashMap::new();
+        let mut through_lake = HashMap::new();
+        let mut donors: HashMap<(usize, usize), usize> = HashMap::new();
+        for tile in &tiles {
+            let mut lake = None;
+            let mut t = flow_direction_map[tile.0][tile.1];
+            while let Some(n) = t {
+                match lake_map[n.0][n.1] {
+                    Some(l) => {
+                        lake = Some(l);
+                        t = flow_direction_map[n.0][n.1];
+                    }
+                    None => break,
+                }
+            }
+            through_lake.insert(*tile, lake);
+            if let Some(n) = t.filter(|n| is_river(n)) {
+                next.insert(*tile, n);
+                *donors.entry(n).or_insert(0) += 1;
+            }
+        }
+
+        // Segments start at sources, confluences and lake outflows
+        let mut is_start = HashMap::new();
+        for tile in &tiles {
+            is_start.insert(*tile, donors.get(tile).copied().unwrap_or(0) != 1);
+        }
+        for tile in &tiles {
+            if let (Some(n), Some(_)) = (next.get(tile), through_lake[tile]) {
+                is_start.insert(*n, true);
+            }
+        }
+
+        let mut network = RiverNetwork::default();
+        for start in tiles.iter().filter(|t| is_start[t]) {
+            let index = network.segments.len();
+            let mut segment_tiles = vec![*start];
+            let mut length = 0.0;
+            let mut tile = *start;
+            network.segment_map.insert(tile, index);
+            loop {
+                match next.get(&tile) {
+                    Some(n) if !is_start[n] => {
+                        length += step_length(tile, *n);
+                        tile = *n;
+                        segment_tiles.push(tile);
+                        network.segment_map.insert(tile, index);
+                    }
+                    Some(n) => {
+                        length += step_length(tile, *n);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            network.segments.push(RiverSegment {
+                tiles: segment_tiles,
+                downstream: None,
+                upstream: vec![],
+                lake: through_lake[&tile],
+                strahler: 1,
+                shreve: 1,
+                length,
+                discharge: flow_accumulation_map[tile.0][tile.1],
+            });
+        }
+
+        // Link segments up now they all have indices
+        for i in 0..network.segments.len() {
+            let end = network.segments[i].end();
+            if let Some(n) = next.get(&end) {
+                let d = network.segment_map[n];
+                network.segments[i].downstream = Some(d);
+                network.segments[d].upstream.push(i);
+            }
+        }
+
+        network.compute_orders();
+        network
+    }
+
+    /// Fill in Strahler and Shreve orders, visiting tributaries before the
+    /// segments they join.
+    fn compute_orders(&mut self) {
+        let mut pending: Vec<usize> = self.segments.iter().map(|s| s.upstream.len()).collect();
+        let mut queue: VecDeque<usize> = (0..self.segments.len())
+            .filter(|i| pending[*i] == 0)
+            .collect();
+
+        while let Some(i) = queue.pop_front() {
+            let upstream = &self.segments[i].upstream;
+            if !upstream.is_empty() {
+                let orders: Vec<u32> = upstream
+                    .iter()
+                    .map(|u| self.segments[*u].strahler)
+                    .collect();
+                let max = *orders.iter().max().unwrap();
+                let strahler = if orders.iter().filter(|o| **o == max).count() > 1 {
+                    max + 1
+                } else {
+                    max
+                };
+                let shreve = upstream.iter().map(|u| self.segments[*u].shreve).sum();
+                self.segments[i].strahler = strahler;
+                self.segments[i].shreve = shreve;
+            }
+
+            if let Some(d) = self.segments[i].downstream {
+                pending[d] -= 1;
+                if pending[d] == 0 {
+                    queue.push_back(d);
+                }
+            }
+        }
+    }
+
+    pub fn segment_at(&self, x: usize, y: usize) -> Option<&RiverSegment> {
+        self.segment_map.get(&(x, y)).map(|i| &self.segments[*i])
+    }
+
+    /// Segments that end in the ocean, off the map or in a lake with no
+    /// river leaving it.
+    pub fn mouths(&self) -> Vec<usize> {
+        (0..self.segments.len())
+            .filter(|i| self.segments[*i].downstream.is_none())
+            .collect()
+    }
+
+    /// Tiles where two or more rivers join.
+    pub fn confluences(&self) -> Vec<(usize, usize)> {
+        self.segments
+            .iter()
+            .filter(|s| s.upstream.len() > 1)
+            .map(|s| s.source())
+            .collect()
+    }
+
+    /// The main stem ending at `segment`, found by following the largest
+    /// tributary upstream at every confluence.  Ordered source first.
+    pub fn main_stem(&self, segment: usize) -> Vec<usize> {
+        let mut stem = vec![segment];
+        let mut current = segment;
+        while let Some(u) = self.segments[current]
+            .upstream
+            .iter()
+            .copied()
+            .max_by(|a, b| {
+                self.segments[*a]
+                    .discharge
+                    .total_cmp(&self.segments[*b].discharge)
+                    .then(b.cmp(a))
+            })
+        {
+            stem.push(u);
+            current = u;
+        }
+        stem.reverse();
+        stem
+    }
+}
@@ -0,0 +1,202 @@
+//! Masks shaping the overall land layout.
+//!
+//! A mask gives every tile a value from 0 (forced under the sea) to 1 (left
+//! as generated), and the height map is pulled down towards -1 in
+//! proportion.
+
+use crate::config::ConfigError;
+use crate::terrain::DynGrid;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+fn island_radius() -> f64 {
+    0.9
+}
+
+fn island_falloff() -> f64 {
+    0.4
+}
+
+fn pangaea_radius() -> f64 {
+    0.8
+}
+
+fn pangaea_uplift() -> f64 {
+    0.4
+}
+
+fn archipelago_islands() -> usize {
+    12
+}
+
+fn archipelago_radius() -> f64 {
+    0.15
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeMask {
+    /// Land fades out towards the map edges.  Distances are fractions of
+    /// the way from the center to the edge.
+    Island {
+        #[serde(default = "island_radius")]
+        radius: f64,
+        #[serde(default = "island_falloff")]
+        falloff: f64,
+    },
+    /// A single large continent: the island mask with the middle of the
+    /// map also raised, so it doesn't break up into islands.
+    Pangaea {
+        #[serde(default = "pangaea_radius")]
+        radius: f64,
+        /// Height added at the center.
+        #[serde(default = "pangaea_uplift")]
+        uplift: f64,
+    },
+    /// Many small islands scattered over the map.
+    Archipelago {
+        #[serde(default = "archipelago_islands")]
+        islands: usize,
+        /// Island size as a fraction of the smaller map dimension.
+        #[serde(default = "archipelago_radius")]
+        radius: f64,
+    },
+    /// Greyscale image, white for land and black for sea, stretched over
+    /// the map.  Needs the `image` feature.
+    Image { path: PathBuf },
+}
+
+/// 0 at `edge`, 1 at `edge - width` and beyond, smooth in between.
+fn falloff(distance: f64, edge: f64, width: f64) -> f64 {
+    let t = ((edge - distance) / width).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Distance from the map center as a fraction of the way to the edge, so
+/// 1 traces an ellipse touching all four sides.
+fn radial(x: usize, y: usize, width: usize, height: usize) -> f64 {
+    let dx = 2.0 * (x as f64 + 0.5) / width as f64 - 1.0;
+    let dy = 2.0 * (y as f64 + 0.5) / height as f64 - 1.0;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Read a greyscale mask image, resized to the map.
+#[cfg(feature = "image")]
+pub(crate) fn load_image(
+    path: &Path,
+    width: usize,
+    height: usize,
+) -> Result<DynGrid<f64>, ConfigError> {
+    use image::imageops::{self, FilterType};
+
+    let image = image::open(path).map_err(|e| ConfigError::MaskImage {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    let luma = imageops::resize(
+        &image.to_luma8(),
+        width as u32,
+        height as u32,
+        FilterType::Triangle,
+    );
+
+    let mut mask = DynGrid::new(width, height, 0.0);
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        mask[x as usize][y as usize] = pixel.0[0] as f64 / 255.0;
+    }
+    Ok(mask)
+}
+
+#[cfg(not(feature = "image"))]
+pub(crate) fn load_image(
+    path: &Path,
+    _width: usize,
+    _height: usize,
+) -> Result<DynGrid<f64>, ConfigError> {
+    Err(ConfigError::MaskImage {
+        path: path.to_path_buf(),
+        reason: "built without the image feature".to_string(),
+    })
+}
+
+impl ShapeMask {
+    /// The mask value of every tile, plus the height `Pangaea` adds.
+    pub fn build<R: Rng>(
+        &self,
+        width: usize,
+        height: usize,
+        rng: &mut R,
+    ) -> Result<(DynGrid<f64>, DynGrid<f64>), ConfigError> {
+        let mut mask = DynGrid::new(width, height, 1.0);
+        let mut uplift = DynGrid::new(width, height, 0.0);
+
+        match self {
+            ShapeMask::Island { radius, falloff: f } => {
+                for x in 0..width {
+                    for y in 0..height {
+                        mask[x][y] = falloff(radial(x, y, width, height), *radius, *f);
+                    }
+                }
+            }
+            ShapeMask::Pangaea { radius, uplift: u } => {
+                for x in 0..width {
+                    for y in 0..height {
+                        let d = radial(x, y, width, height);
+                        mask[x][y] = falloff(d, *radius, 0.5 * radius);
+                        uplift[x][y] = u * falloff(d, *radius, *radius);
+                    }
+                }
+            }
+            ShapeMask::Archipelago { islands, radius } => {
+                let size = radius * width.min(height) as f64;
+                let margin = size.min(width.min(height) as f64 / 2.0);
+                let centers: Vec<(f64, f64)> = (0..*islands)
+                    .map(|_| {
+                        (
+                            rng.gen_range(margin..=width as f64 - margin),
+                            rng.gen_range(margin..=height as f64 - margin),
+                        )
+                    })
+                    .collect();
+                for x in 0..width {
+                    for y in 0..height {
+                        mask[x][y] = centers
+                            .iter()
+                            .map(|c| {
+                                let d = ((x as f64 + 0.5 - c.0).powi(2)
+                                    + (y as f64 + 0.5 - c.1).powi(2))
+                                .sqrt();
+                                falloff(d, size, size / 2.0)
+                            })
+                            .fold(0.0, f64::max);
+                    }
+                }
+            }
+            ShapeMask::Image { path } => mask = load_image(path, width, height)?,
+        }
+
+        Ok((mask, uplift))
+    }
+}
+
+/// Mask forcing the outermost `border` tiles under the sea, fading in over
+/// as many tiles again.
+pub fn ocean_border(width: usize, height: usize, border: usize) -> DynGrid<f64> {
+    let mut mask = DynGrid::new(width, height, 1.0);
+    if border == 0 {
+        return mask;
+    }
+    for x in 0..width {
+        for y in 0..height {
+            let edge = x.min(y).min(width - 1 - x).min(height - 1 - y);
+            mask[x][y] = if edge < border {
+                0.0
+            } else {
+                let t = ((edge + 1 - border) as f64 / border as f64).min(1.0);
+                t * t * (3.0 - 2.0 * t)
+            };
+        }
+    }
+    mask
+}
@@ -1,10 +1,25 @@
-use noise::{Fbm, MultiFractal, NoiseFn, Simplex};
-use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use crate::bathymetry::{self, Bathymetry, Seafloor};
+use crate::biomes::{BiomeTable, TileConditions};
+use crate::climate::{self, ClimateUnits, Continentality, MoistureModel, Planet, Seasons, MONTHS};
+use crate::currents::{self, OceanCurrents};
+use crate::erosion::{self, HydraulicErosion, ThermalErosion};
+use crate::heightmap::HeightmapFile;
+use crate::hydrology::{self, Lake};
+use crate::koppen::Koppen;
+use crate::noise_graph::{Fractal, NoiseNode};
+use crate::plates::{self, Plate, TectonicPlates};
+use crate::rivers::RiverNetwork;
+use crate::shapes::{self, ShapeMask};
+use crate::watersheds::Watersheds;
+use noise::NoiseFn;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
 
 // https://en.wikipedia.org/wiki/Biome#/media/File:Climate_influence_on_terrestrial_biome.svg
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Biome {
     Tundra,
     BorealForest,
@@ -15,15 +30,102 @@ pub enum Biome {
     TropicalRainforest,
     Savanna,
     SubtropicalDesert,
+    IceSheet,
+    Steppe,
+    /// Bare rock and meadow above the tree line.
+    Alpine,
+    /// Mountain forest below the tree line.
+    Montane,
+    Wetland,
+    Beach,
+    Mangrove,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 16] = [
+        Biome::Tundra,
+        Biome::BorealForest,
+        Biome::TemperateRainforest,
+        Biome::TemperateSeasonalForest,
+        Biome::Shrubland,
+        Biome::ColdDesert,
+        Biome::TropicalRainforest,
+        Biome::Savanna,
+        Biome::SubtropicalDesert,
+        Biome::IceSheet,
+        Biome::Steppe,
+        Biome::Alpine,
+        Biome::Montane,
+        Biome::Wetland,
+        Biome::Beach,
+        Biome::Mangrove,
+    ];
+
+    /// Display colour used by both the terminal and image renderers.
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            Biome::Tundra => [147, 168, 173],
+            Biome::BorealForest => [0, 80, 70],
+            Biome::TemperateRainforest => [25, 55, 0],
+            Biome::TemperateSeasonalForest => [145, 215, 70],
+            Biome::Shrubland => [130, 150, 100],
+            Biome::ColdDesert => [210, 190, 140],
+            Biome::TropicalRainforest => [48, 127, 55],
+            Biome::Savanna => [202, 139, 43],
+            Biome::SubtropicalDesert => [245, 200, 80],
+            Biome::IceSheet => [235, 245, 250],
+            Biome::Steppe => [185, 180, 110],
+            Biome::Alpine => [150, 140, 135],
+            Biome::Montane => [70, 95, 60],
+            Biome::Wetland => [60, 105, 90],
+            Biome::Beach => [240, 225, 170],
+            Biome::Mangrove => [35, 85, 55],
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Feature {
     RiverSource,
     River,
+    Lake,
     Ocean,
 }
 
+/// Collect the (up to eight) in-bounds neighbors of `point` on a `width` x
+/// `height` grid.
+fn neighbors(point: &(usize, usize), width: usize, height: usize) -> Vec<(usize, usize)> {
+    let mut output = vec![];
+
+    if point.0 + 1 < width {
+        output.push((point.0 + 1, point.1));
+        if point.1 + 1 < height {
+            output.push((point.0 + 1, point.1 + 1));
+        }
+        if point.1 > 0 {
+            output.push((point.0 + 1, point.1 - 1));
+        }
+    }
+    if point.0 > 0 {
+        output.push((point.0 - 1, point.1));
+        if point.1 + 1 < height {
+            output.push((point.0 - 1, point.1 + 1));
+        }
+        if point.1 > 0 {
+            output.push((point.0 - 1, point.1 - 1));
+        }
+    }
+    if point.1 > 0 {
+        output.push((point.0, point.1 - 1));
+    }
+    if point.1 + 1 < height {
+        output.push((point.0, point.1 + 1));
+    }
+
+    output
+}
+
+/// Fixed size grid stored inline, dimensions known at compile time.
 #[derive(Clone)]
 pub struct Grid<T, const X: usize, const Y: usize> {
     value: [[T; Y]; X],
@@ -43,46 +145,11 @@ impl<T: Copy, const X: usize, const Y: usize> Grid<T, X, Y> {
     }
 
     pub fn get_neighbors(&self, point: &(usize, usize)) -> Vec<(usize, usize)> {
-        let mut output = vec![];
-
-        if point.0 + 1 < X {
-            output.push((point.0 + 1, point.1));
-            if point.1 + 1 < Y {
-                output.push((point.0 + 1, point.1 + 1));
-            }
-            if point.1 > 0 {
-                output.push((point.0 + 1, point.1 - 1));
-            }
-        }
-        if point.0 > 0 {
-            output.push((point.0 - 1, point.1));
-            if point.1 + 1 < Y {
-                output.push((point.0 - 1, point.1 + 1));
-            }
-            if point.1 > 0 {
-                output.push((point.0 - 1, point.1 - 1));
-            }
-        }
-        if point.1 > 0 {
-            output.push((point.0, point.1 - 1));
-        }
-        if point.1 + 1 < Y {
-            output.push((point.0, point.1 + 1));
-        }
-
-        output
+        neighbors(point, X, Y)
     }
 
     pub fn to_vecs(&self) -> Vec<Vec<T>> {
-        let mut output = vec![];
-        for row in &self.value {
-            let mut temp = vec![];
-            for t in row {
-                temp.push(*t);
-            }
-            output.push(temp);
-        }
-        output
+        self.value.iter().map(|row| row.to_vec()).collect()
     }
 }
 
@@ -100,91 +167,101 @@ impl<T, const X: usize, const Y: usize> IndexMut<usize> for Grid<T, X, Y> {
     }
 }
 
-pub struct Lake<const X: usize, const Y: usize> {
-    height_map: Grid<f64, X, Y>,
-    tiles: HashSet<(usize, usize)>,
-    perimeter: HashSet<(usize, usize)>,
+/// Heap backed grid with dimensions chosen at runtime.
+///
+/// Stored column-major so `grid[x][y]` indexes the same way as `Grid`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynGrid<T> {
+    width: usize,
+    height: usize,
+    value: Vec<T>,
 }
 
-impl<const X: usize, const Y: usize> Lake<X, Y> {
-    pub fn new(height_map: Grid<f64, X, Y>) -> Lake<X, Y> {
-        Lake {
-            height_map: height_map,
-            tiles: HashSet::new(),
-            perimeter: HashSet::new(),
+impl<T: Copy> DynGrid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> DynGrid<T> {
+        DynGrid {
+            width,
+            height,
+            value: vec![fill; width * height],
         }
     }
 
-    pub fn _insert(&mut self, tile: (usize, usize)) {
-        self.tiles.insert(tile);
-        self.perimeter.remove(&tile);
-        let neighbors = self.height_map.get_neighbors(&tile);
+    pub fn width(&self) -> usize {
+        self.width
+    }
 
-        for n in neighbors {
-            if !self.tiles.contains(&n) {
-                self.perimeter.insert(n);
-            }
-        }
+    pub fn height(&self) -> usize {
+        self.height
     }
 
-    pub fn fill(
-        &mut self,
-        tile: (usize, usize),
-        ocean_height: f64,
-        river_tile_limit: usize,
-    ) -> Vec<(usize, usize)> {
-        let mut tile_cntr = 0;
+    pub fn get_neighbors(&self, point: &(usize, usize)) -> Vec<(usize, usize)> {
+        neighbors(point, self.width, self.height)
+    }
 
-        self._insert(tile);
-        tile_cntr += 1;
+    pub fn to_vecs(&self) -> Vec<Vec<T>> {
+        (0..self.width).map(|x| self[x].to_vec()).collect()
+    }
+}
 
-        loop {
-            // Find lowest height perimeter tile
-            let mut min_tile = tile;
-            let mut min_height = f64::MAX;
-            for p in self.perimeter.iter() {
-                let h = self.height_map[p.0][p.1];
-                if min_height > h {
-                    min_tile = p.clone();
-                    min_height = h;
-                }
-            }
+impl<T: Copy, const X: usize, const Y: usize> From<Grid<T, X, Y>> for DynGrid<T> {
+    fn from(grid: Grid<T, X, Y>) -> DynGrid<T> {
+        DynGrid {
+            width: X,
+            height: Y,
+            value: grid.value.iter().flatten().copied().collect(),
+        }
+    }
+}
 
-            // If we've hit the ocean, we're done
-            if min_height < ocean_height {
-                break;
-            }
-            if tile_cntr >= river_tile_limit {
-                break;
-            }
+impl<T> Index<usize> for DynGrid<T> {
+    type Output = [T];
 
-            self._insert(min_tile);
-            tile_cntr += 1;
-        }
+    fn index(&self, x: usize) -> &Self::Output {
+        assert!(x < self.width, "x index {} out of bounds", x);
+        &self.value[x * self.height..(x + 1) * self.height]
+    }
+}
 
-        self.tiles.remove(&tile);
-        self.tiles.iter().map(|x| *x).collect()
+impl<T> IndexMut<usize> for DynGrid<T> {
+    fn index_mut(&mut self, x: usize) -> &mut Self::Output {
+        assert!(x < self.width, "x index {} out of bounds", x);
+        &mut self.value[x * self.height..(x + 1) * self.height]
     }
 }
 
-pub fn temp_map_value_to_degrees_c(value: f64) -> f64 {
-    // Input values on range [-1.0, 1.0]... map to [-10.0, 32.0]
-    let min_temp = -10.0;
-    let max_temp = 32.0;
-    let scaler = (max_temp - min_temp) / 2.0;
-    scaler * (value + 1.0) + min_temp
+/// Generation stages, each of which draws from its own sub-seed so that
+/// changing one stage doesn't perturb the others.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Stage {
+    Height = 1,
+    Precipitation = 2,
+    Temperature = 3,
+    Erosion = 4,
+    Plates = 5,
+    Shape = 6,
 }
 
-pub fn precip_map_value_to_cm_rainfall(value: f64, temperature: f64) -> f64 {
-    // Input values on range [-1.0, 1.0]... map to [0.0, 450.0].
-    // Temperature values on range [-1.0, 1.0] as well.
-    // Scale by temperature, basically precip * temp = precip, approximately
-    let min_temp = 0.0;
-    let max_temp = 450.0;
-    let scaler = (max_temp - min_temp) / 2.0;
-    (scaler * (value + 1.0) - min_temp) * ((temperature + 1.0) / 2.0)
+impl Stage {
+    /// Derive this stage's sub-seed from the world seed (splitmix64 finalizer).
+    pub(crate) fn seed(self, seed: u32) -> u64 {
+        let mut z = (seed as u64) ^ (self as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn noise_seed(self, seed: u32) -> u32 {
+        self.seed(seed) as u32
+    }
+
+    pub(crate) fn rng(self, seed: u32) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(self.seed(seed))
+    }
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AutoGenConfig {
     pub x_scale: f64,
     pub y_scale: f64,
@@ -193,35 +270,225 @@ pub struct AutoGenConfig {
     pub precip_offset: f64,
     pub temperature_frequency: f64,
     pub temperature_offset: f64,
+    /// Noise graphs replacing the plain `Fbm` at `landmass_frequency`,
+    /// `precip_frequency` and `temperature_frequency` for their layers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height_noise: Option<NoiseNode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precip_noise: Option<NoiseNode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature_noise: Option<NoiseNode>,
+    /// File the height map is read from instead of generated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heightmap: Option<HeightmapFile>,
+    /// Height below which tiles are ocean, unless `land_fraction` is set.
     pub ocean_height: f64,
-    pub river_tile_limit: usize,
+    /// Share of the map left above the sea.  When set, sea level is placed
+    /// at the matching height percentile instead of at `ocean_height`, so
+    /// land coverage stays the same from seed to seed.  Tiles masked out by
+    /// `shape` or `ocean_border` don't count towards the share.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub land_fraction: Option<f64>,
+    /// Overall land layout, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<ShapeMask>,
+    /// Width in tiles of the ocean kept clear around the map edge.
+    pub ocean_border: usize,
+    /// Depths splitting the ocean floor into shelf, slope, abyssal plain
+    /// and trenches.
+    pub bathymetry: Bathymetry,
+    /// Upstream runoff (in tiles of full rainfall) a tile needs to carry a
+    /// river.
+    pub river_threshold: f64,
+    /// Depressions shallower than this (in height units) don't hold lakes.
+    pub lake_min_depth: f64,
+    /// Runoff (in tiles of full rainfall) each lake tile loses to
+    /// evaporation.  Lakes that can't replace it have no outflow.
+    pub lake_evaporation: f64,
+    /// Plate tectonics blended into the noise height map, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plates: Option<TectonicPlates>,
+    /// Droplet erosion run on the fresh height map, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hydraulic_erosion: Option<HydraulicErosion>,
+    /// Talus slope relaxation run after any hydraulic erosion, off when
+    /// `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thermal_erosion: Option<ThermalErosion>,
+    /// Latitudes the map spans and the planet's axial tilt and temperature
+    /// scale.
+    pub planet: Planet,
+    /// Temperature and rainfall ranges, and cooling with height.
+    pub units: ClimateUnits,
+    /// Rain carried in from the ocean by the prevailing winds and wrung out
+    /// over mountains, blended into the precipitation noise.  Off when
+    /// `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moisture: Option<MoistureModel>,
+    /// Milder, wetter coasts and drier interiors with wider temperature
+    /// swings, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continentality: Option<Continentality>,
+    /// Warm and cold ocean currents shifting coastal temperature and
+    /// rainfall, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currents: Option<OceanCurrents>,
+    /// Monthly climate and Köppen classification, off when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seasons: Option<Seasons>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub seed: Option<u32>,
+    /// Climate to biome classification, the Whittaker diagram by default.
+    pub biomes: BiomeTable,
 }
 
-pub struct Landmass<const X: usize, const Y: usize> {
-    pub height_map: Grid<f64, X, Y>,
-    pub precip_map: Grid<f64, X, Y>,
-    pub biome_map: Grid<Biome, X, Y>,
-    pub temperature_map: Grid<f64, X, Y>,
+impl Default for AutoGenConfig {
+    fn default() -> AutoGenConfig {
+        AutoGenConfig {
+            x_scale: 200.,
+            y_scale: 100.,
+            landmass_frequency: 4.0,
+            precip_frequency: 6.0,
+            precip_offset: 0.0,
+            temperature_frequency: 2.0,
+            temperature_offset: 0.0,
+            height_noise: None,
+            precip_noise: None,
+            temperature_noise: None,
+            heightmap: None,
+            ocean_height: -1.0,
+            land_fraction: None,
+            shape: None,
+            ocean_border: 0,
+            bathymetry: Bathymetry::default(),
+            river_threshold: 30.0,
+            lake_min_depth: 0.01,
+            lake_evaporation: 1.0,
+            plates: None,
+            hydraulic_erosion: None,
+            thermal_erosion: None,
+            planet: Planet::default(),
+            units: ClimateUnits::default(),
+            moisture: None,
+            continentality: None,
+            currents: None,
+            seasons: None,
+            seed: None,
+            biomes: BiomeTable::default(),
+        }
+    }
+}
+
+/// Build a layer's noise graph, plain `Fbm` at `frequency` unless the config
+/// has one.
+fn layer_noise(graph: &Option<NoiseNode>, frequency: f64, seed: u32) -> Box<dyn NoiseFn<f64, 2>> {
+    match graph {
+        Some(node) => node.build(seed),
+        None => NoiseNode::Fbm(Fractal::with_frequency(frequency)).build(seed),
+    }
+}
+
+pub struct Landmass {
+    pub height_map: DynGrid<f64>,
+    /// Annual rainfall in cm.
+    pub precip_map: DynGrid<f64>,
+    pub biome_map: DynGrid<Biome>,
+    /// Annual mean temperature in °C.
+    pub temperature_map: DynGrid<f64>,
+    /// Mean temperature of each month in °C, January first.  Empty when
+    /// seasons are off.
+    pub monthly_temperature: Vec<DynGrid<f64>>,
+    /// Precipitation of each month in cm, January first.  Empty when
+    /// seasons are off.
+    pub monthly_precipitation: Vec<DynGrid<f64>>,
+    /// Köppen–Geiger climate of each land tile, `None` at sea and
+    /// everywhere when seasons are off.
+    pub koppen_map: DynGrid<Option<Koppen>>,
+    /// Floor under each ocean tile, `None` on land and in lakes.
+    pub seafloor_map: DynGrid<Option<Seafloor>>,
+    pub plates: Vec<Plate>,
+    /// Index into `plates` of the plate under each tile, `None` everywhere
+    /// when plates are off.
+    pub plate_map: DynGrid<Option<usize>>,
+    /// Depth of material laid down by erosion.
+    pub sediment_map: DynGrid<f64>,
+    /// Height below which tiles are ocean, from `ocean_height` or
+    /// `land_fraction`.
+    pub sea_level: f64,
+    /// Tiles the shape mask and `ocean_border` keep under the sea.
+    pub masked_map: DynGrid<bool>,
+    /// Distance in tiles to the nearest ocean tile.
+    pub coast_distance_map: DynGrid<f64>,
+    /// Temperature shift in °C from ocean currents, warm above 0 and cold
+    /// below: the sea surface anomaly at sea and its effect on the air over
+    /// the coasts.  All 0 when currents are off.
+    pub current_map: DynGrid<f64>,
+    /// `height_map` with depressions raised to their spill level.
+    pub filled_height_map: DynGrid<f64>,
+    /// Neighbor each tile drains to, `None` for ocean and map edge outlets.
+    pub flow_direction_map: DynGrid<Option<(usize, usize)>>,
+    /// Runoff collected from each tile's upstream catchment.
+    pub flow_accumulation_map: DynGrid<f64>,
+    pub lakes: Vec<Lake>,
+    /// Index into `lakes` of the lake covering each tile.
+    pub lake_map: DynGrid<Option<usize>>,
+    pub river_network: RiverNetwork,
+    pub watersheds: Watersheds,
     pub features: HashMap<(usize, usize), Feature>,
-    pub render: bool,
+    /// World seed used when `AutoGenConfig::seed` is `None`, and the seed the
+    /// last `autogen` ran with.
+    pub seed: u32,
 }
 
-impl<const X: usize, const Y: usize> Landmass<X, Y> {
-    pub fn new() -> Landmass<X, Y> {
+impl Landmass {
+    pub fn new(width: usize, height: usize) -> Landmass {
         Landmass {
-            height_map: Grid::<f64, X, Y>::new([[0.0; Y]; X]),
-            precip_map: Grid::<f64, X, Y>::new([[0.0; Y]; X]),
-            biome_map: Grid::<Biome, X, Y>::new([[Biome::Tundra; Y]; X]),
-            temperature_map: Grid::<f64, X, Y>::new([[0.0; Y]; X]),
+            height_map: DynGrid::new(width, height, 0.0),
+            precip_map: DynGrid::new(width, height, 0.0),
+            biome_map: DynGrid::new(width, height, Biome::Tundra),
+            temperature_map: DynGrid::new(width, height, 0.0),
+            monthly_temperature: vec![],
+            monthly_precipitation: vec![],
+            koppen_map: DynGrid::new(width, height, None),
+            seafloor_map: DynGrid::new(width, height, None),
+            plates: vec![],
+            plate_map: DynGrid::new(width, height, None),
+            sediment_map: DynGrid::new(width, height, 0.0),
+            sea_level: -1.0,
+            masked_map: DynGrid::new(width, height, false),
+            coast_distance_map: DynGrid::new(width, height, f64::INFINITY),
+            current_map: DynGrid::new(width, height, 0.0),
+            filled_height_map: DynGrid::new(width, height, 0.0),
+            flow_direction_map: DynGrid::new(width, height, None),
+            flow_accumulation_map: DynGrid::new(width, height, 0.0),
+            lakes: vec![],
+            lake_map: DynGrid::new(width, height, None),
+            river_network: RiverNetwork::default(),
+            watersheds: Watersheds::default(),
             features: HashMap::new(),
-            render: false,
+            seed: rand::thread_rng().gen(),
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.height_map.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.height_map.height()
+    }
+
+    pub fn lake_at(&self, x: usize, y: usize) -> Option<&Lake> {
+        self.lake_map[x][y].map(|i| &self.lakes[i])
+    }
+
+    fn world_seed(&self, config: &AutoGenConfig) -> u32 {
+        config.seed.unwrap_or(self.seed)
+    }
+
     pub fn populate_ocean(&mut self, ocean_height: f64) {
-        for x in 0..X {
-            for y in 0..Y {
+        for x in 0..self.width() {
+            for y in 0..self.height() {
                 if self.height_map[x][y] < ocean_height {
                     self.features.insert((x, y), Feature::Ocean);
                 }
@@ -229,59 +496,262 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
         }
     }
 
+    /// Generate the height map from noise (or read it from the configured
+    /// file), blend in any tectonic plates and shape it with the configured
+    /// masks.
+    ///
+    /// # Panics
+    ///
+    /// If the heightmap file or an image shape mask can't be read, which
+    /// `AutoGenConfig::validate` checks for.
     pub fn generate_height_map(&mut self, config: &AutoGenConfig) {
-        // Generate the height_map
-        let g = Fbm::<Simplex>::default();
-        let g = g.set_frequency(config.landmass_frequency);
-        for x in 0..X {
-            for y in 0..Y {
-                self.height_map[x][y] =
-                    g.get([x as f64 / config.x_scale, y as f64 / config.y_scale]);
+        if let Some(file) = &config.heightmap {
+            self.height_map = file
+                .load(self.width(), self.height())
+                .expect("failed to load heightmap");
+        } else {
+            let seed = Stage::Height.noise_seed(self.world_seed(config));
+            let g = layer_noise(&config.height_noise, config.landmass_frequency, seed);
+            for x in 0..self.width() {
+                for y in 0..self.height() {
+                    self.height_map[x][y] =
+                        g.get([x as f64 / config.x_scale, y as f64 / config.y_scale]);
+                }
+            }
+        }
+
+        self.blend_plates(config);
+
+        self.masked_map = self.apply_shape(config);
+
+        self.update_sea_level(config);
+    }
+
+    /// Set `sea_level` for the current height map and sink any masked out
+    /// tiles that ended up above it.
+    pub fn update_sea_level(&mut self, config: &AutoGenConfig) {
+        self.sea_level = match config.land_fraction {
+            Some(fraction) => {
+                hydrology::sea_level_for_land_fraction(&self.height_map, &self.masked_map, fraction)
+            }
+            None => config.ocean_height,
+        };
+
+        let below = self.sea_level.next_down();
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                if self.masked_map[x][y] {
+                    self.height_map[x][y] = self.height_map[x][y].min(below);
+                }
+            }
+        }
+    }
+
+    /// Height that altitude is measured up from: sea level, or 0 when the
+    /// sea lies lower, so that land below height 0 always counts as lowland.
+    pub fn lowland_level(&self) -> f64 {
+        self.sea_level.max(0.0)
+    }
+
+    /// Pull masked out tiles under the sea, returning the tiles masked out
+    /// completely.
+    fn apply_shape(&mut self, config: &AutoGenConfig) -> DynGrid<bool> {
+        let (width, height) = (self.width(), self.height());
+        let (mut mask, uplift) = match &config.shape {
+            Some(shape) => {
+                let mut rng = Stage::Shape.rng(self.world_seed(config));
+                shape
+                    .build(width, height, &mut rng)
+                    .expect("failed to build shape mask")
             }
+            None => (
+                DynGrid::new(width, height, 1.0),
+                DynGrid::new(width, height, 0.0),
+            ),
+        };
+        let border = shapes::ocean_border(width, height, config.ocean_border);
+        let mut masked = DynGrid::new(width, height, false);
+
+        for x in 0..width {
+            for y in 0..height {
+                mask[x][y] *= border[x][y];
+                let h = (self.height_map[x][y] + uplift[x][y]).min(1.0);
+                self.height_map[x][y] = -1.0 + (h + 1.0) * mask[x][y];
+                masked[x][y] = mask[x][y] <= 0.0;
+            }
+        }
+        masked
+    }
+
+    /// Split the map into tectonic plates and blend their continents,
+    /// mountain ranges and rifts into the height map.
+    fn blend_plates(&mut self, config: &AutoGenConfig) {
+        let (width, height) = (self.width(), self.height());
+        self.plates = vec![];
+        self.plate_map = DynGrid::new(width, height, None);
+
+        let params = match &config.plates {
+            Some(params) => params,
+            None => return,
+        };
+        let seed = self.world_seed(config);
+        let mut rng = Stage::Plates.rng(seed);
+        let (plates, plate_map) = plates::generate_plates(
+            width,
+            height,
+            params,
+            Stage::Plates.noise_seed(seed),
+            &mut rng,
+        );
+        let relief = plates::plate_relief(&plates, &plate_map, params);
+
+        for x in 0..width {
+            for y in 0..height {
+                let h = (1.0 - params.blend) * self.height_map[x][y] + params.blend * relief[x][y];
+                self.height_map[x][y] = h.clamp(-1.0, 1.0);
+                self.plate_map[x][y] = Some(plate_map[x][y]);
+            }
+        }
+        self.plates = plates;
+    }
+
+    /// Run the erosion stages enabled in `config` over the height map.
+    pub fn erode(&mut self, config: &AutoGenConfig) {
+        self.sediment_map = DynGrid::new(self.width(), self.height(), 0.0);
+
+        if let Some(params) = &config.hydraulic_erosion {
+            let mut rng = Stage::Erosion.rng(self.world_seed(config));
+            erosion::hydraulic_erosion(
+                &mut self.height_map,
+                &mut self.sediment_map,
+                params,
+                self.sea_level,
+                &mut rng,
+            );
         }
+
+        if let Some(params) = &config.thermal_erosion {
+            erosion::thermal_erosion(&mut self.height_map, &mut self.sediment_map, params);
+        }
+
+        // Erosion moves material around, keep the land fraction on target
+        self.update_sea_level(config);
+    }
+
+    /// Measure how far every tile is from the sea.
+    pub fn generate_coast_distance_map(&mut self) {
+        self.coast_distance_map = climate::coast_distance(&self.height_map, self.sea_level);
+    }
+
+    /// Classify the floor under the `Feature::Ocean` tiles, so this runs
+    /// after `populate_ocean`.
+    pub fn generate_seafloor_map(&mut self, config: &AutoGenConfig) {
+        let (width, height) = (self.width(), self.height());
+        let mut ocean = DynGrid::new(width, height, false);
+        for x in 0..width {
+            for y in 0..height {
+                ocean[x][y] = self.features.get(&(x, y)) == Some(&Feature::Ocean);
+            }
+        }
+        self.seafloor_map = bathymetry::classify_seafloor(
+            &self.height_map,
+            &ocean,
+            self.sea_level,
+            &config.bathymetry,
+        );
     }
 
+    /// Trace the ocean currents along the coastline of the `Feature::Ocean`
+    /// tiles, so this runs after `populate_ocean`.
+    pub fn generate_current_map(&mut self, config: &AutoGenConfig) {
+        let (width, height) = (self.width(), self.height());
+        self.current_map = DynGrid::new(width, height, 0.0);
+        let params = match &config.currents {
+            Some(params) => params,
+            None => return,
+        };
+        let mut ocean = DynGrid::new(width, height, false);
+        for x in 0..width {
+            for y in 0..height {
+                ocean[x][y] = self.features.get(&(x, y)) == Some(&Feature::Ocean);
+            }
+        }
+        self.current_map = currents::current_anomaly(&ocean, &config.planet, &config.units, params);
+    }
+
+    /// Expects `generate_temperature_map` to have run, and
+    /// `generate_coast_distance_map` too when continentality is on.
     pub fn generate_precipitation_map(&mut self, config: &AutoGenConfig) {
-        // Generate the precip_map
-        let mut rng = rand::thread_rng();
-        let seed: u32 = rng.gen();
-        let g = Fbm::<Simplex>::new(seed);
-        let g = g.set_frequency(config.precip_frequency);
-        for x in 0..X {
-            for y in 0..Y {
-                self.precip_map[x][y] = 1.5
+        // Work on the [-1, 1] noise scale, converted to cm at the end
+        let seed = Stage::Precipitation.noise_seed(self.world_seed(config));
+        let g = layer_noise(&config.precip_noise, config.precip_frequency, seed);
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                self.precip_map[x][y] = (1.5
                     * g.get([x as f64 / config.x_scale, y as f64 / config.y_scale])
-                    + config.precip_offset;
-                if self.precip_map[x][y] > 1.0 {
-                    self.precip_map[x][y] = 1.0;
-                } else if self.precip_map[x][y] < -1.0 {
-                    self.precip_map[x][y] = -1.0;
+                    + config.precip_offset)
+                    .clamp(-1.0, 1.0);
+            }
+        }
+
+        if let Some(params) = &config.moisture {
+            let wind = climate::wind_precipitation(
+                &self.height_map,
+                self.sea_level,
+                &config.planet,
+                params,
+            );
+            for x in 0..self.width() {
+                for y in 0..self.height() {
+                    let p =
+                        (1.0 - params.blend) * self.precip_map[x][y] + params.blend * wind[x][y];
+                    self.precip_map[x][y] = p.clamp(-1.0, 1.0);
+                }
+            }
+        }
+
+        if let Some(params) = &config.continentality {
+            for x in 0..self.width() {
+                for y in 0..self.height() {
+                    let c = params.at(self.coast_distance_map[x][y]);
+                    let p = self.precip_map[x][y];
+                    self.precip_map[x][y] =
+                        -1.0 + (p + 1.0) * (1.0 - params.precipitation_loss * c);
+                }
+            }
+        }
+
+        if let Some(params) = &config.currents {
+            for x in 0..self.width() {
+                for y in 0..self.height() {
+                    let shift = params.precipitation_effect * self.current_map[x][y];
+                    self.precip_map[x][y] = (self.precip_map[x][y] + shift).clamp(-1.0, 1.0);
                 }
             }
         }
+
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                let temp = self.temperature_map[x][y];
+                self.precip_map[x][y] = config.units.rainfall_cm(self.precip_map[x][y], temp);
+            }
+        }
     }
 
+    /// Expects `generate_coast_distance_map` to have run when continentality
+    /// is on, and `generate_current_map` when currents are.
     pub fn generate_temperature_map(&mut self, config: &AutoGenConfig) {
-        // Generate the temperature_map in degrees C
-        let mut rng = rand::thread_rng();
-        let g = Fbm::<Simplex>::new(rng.gen());
-        let g = g.set_frequency(config.temperature_frequency);
-        for x in 0..X {
-            for y in 0..Y {
-                // Let natural temp be 0.8 at equator, -0.5 at poles
-                let mut temp = -2.6 * ((y as f64) / (Y as f64) - 0.5).abs() + 0.8;
-
-                // Consider height map (higher altitude -> lower temp)
-                let h = self.height_map[x][y];
-                if h > 0.8 {
-                    // Tall mountains (range of ~45, so 0.1 = 2.25 deg. C)
-                    temp -= 10.0 * (h - 0.8) + 0.9;
-                } else if h > 0.7 {
-                    // Foothills
-                    temp -= 5.0 * (h - 0.7) + 0.4;
-                } else if h > 0.0 {
-                    temp -= 0.8 * h;
-                }
+        let seed = Stage::Temperature.noise_seed(self.world_seed(config));
+        let g = layer_noise(
+            &config.temperature_noise,
+            config.temperature_frequency,
+            seed,
+        );
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                let latitude = config.planet.latitude(y, self.height());
+                let base = config.planet.base_temperature(latitude);
+                let mut temp = base;
 
                 // Get a random [-0.5, 0.5] value
                 temp += g.get([x as f64 / config.x_scale, y as f64 / config.y_scale]) / 2.0;
@@ -289,154 +759,292 @@ impl<const X: usize, const Y: usize> Landmass<X, Y> {
                 // Factor in config
                 temp += config.temperature_offset;
 
-                // Saturate to [-1.0, 1.0]
-                if temp > 1.0 {
-                    temp = 1.0;
-                } else if temp < -1.0 {
-                    temp = -1.0;
+                // The sea evens out the heat of the tropics and the cold of
+                // the high latitudes, interiors go further towards both
+                if let Some(params) = &config.continentality {
+                    let c = params.at(self.coast_distance_map[x][y]);
+                    let departure = base - config.planet.mean_temperature();
+                    temp += params.temperature_contrast * (2.0 * c - 1.0) * departure;
                 }
 
-                // Assign when done
-                self.temperature_map[x][y] = temp;
+                // Saturate to [-1.0, 1.0], then cool with altitude in °C.
+                let cooling = config
+                    .units
+                    .lapse_cooling(self.height_map[x][y], self.lowland_level());
+                self.temperature_map[x][y] = config.units.degrees_c(temp.clamp(-1.0, 1.0))
+                    - cooling
+                    + self.current_map[x][y];
             }
         }
     }
 
-    pub fn generate_biome_map(&mut self) {
-        // Generate the biome map
-        for x in 0..X {
-            for y in 0..Y {
-                let norm_temp = self.temperature_map[x][y];
-                let temp = temp_map_value_to_degrees_c(norm_temp);
-                let norm_precip = self.precip_map[x][y];
-                let precip = precip_map_value_to_cm_rainfall(norm_precip, norm_temp);
-
-                // Anything below 0.0 C is Tundra, roughly
-                let mut biome = Biome::Tundra;
+    /// Spread the annual temperature and precipitation over the months and
+    /// classify the climate of every land tile.  Runs after both annual maps
+    /// and `generate_coast_distance_map`.
+    pub fn generate_seasons(&mut self, config: &AutoGenConfig) {
+        let (width, height) = (self.width(), self.height());
+        self.monthly_temperature = vec![];
+        self.monthly_precipitation = vec![];
+        self.koppen_map = DynGrid::new(width, height, None);
 
-                // Boreal forest
-                if temp < 7.0 && temp > 0.0 && precip > 40.0 {
-                    biome = Biome::BorealForest;
-                }
+        let params = match &config.seasons {
+            Some(params) => params,
+            None => return,
+        };
+        let reach = config.continentality.clone().unwrap_or_default();
+        let mut continentality = DynGrid::new(width, height, 0.0);
+        for x in 0..width {
+            for y in 0..height {
+                continentality[x][y] = reach.at(self.coast_distance_map[x][y]);
+            }
+        }
+        let (temperature, precipitation) = climate::monthly_climate(
+            &self.temperature_map,
+            &self.precip_map,
+            &continentality,
+            &config.planet,
+            params,
+        );
 
-                // Temperate grassland/Cold desert
-                if temp > 0.0 && temp < 22.0 && precip < 50.0 {
-                    biome = Biome::ColdDesert;
+        for x in 0..width {
+            for y in 0..height {
+                if self.height_map[x][y] < self.sea_level {
+                    continue;
                 }
-
-                // Woodland/Shrubland 50 cm at 7 C, 120 cm at 22 C
-                // 120 - 50 = 70, 22 - 7 = 15, slope = 70 / 15 = 4.67
-                // y-intercept = 50 - 7 * 4.67 = 50 - 32.67 = 17.33
-                if temp > 7.0 && temp < 22.0 && precip > 50.0 && precip < 17.33 + 4.67 * temp {
-                    biome = Biome::Shrubland;
-                }
-
-                // Temperate seasonal forest 170 cm at 7 C, 230 at 22 C
-                // 230 - 170 = 60, 22 - 7 = 15, slope = 60 / 15 = 4
-                // y-intercept = 170 - 7 * 4 = 170 - 28 = 142
-                if temp > 7.0
-                    && temp < 22.0
-                    && precip > 17.33 + 4.67 * temp
-                    && precip < 170.0 + 4.0 * temp
-                {
-                    biome = Biome::TemperateSeasonalForest;
+                let mut temps = [0.0; MONTHS];
+                let mut precips = [0.0; MONTHS];
+                for month in 0..MONTHS {
+                    temps[month] = temperature[month][x][y];
+                    // Köppen thresholds are in mm
+                    precips[month] = 10.0 * precipitation[month][x][y];
                 }
+                let northern = config.planet.latitude(y, height) >= 0.0;
+                self.koppen_map[x][y] = Some(Koppen::classify(&temps, &precips, northern));
+            }
+        }
 
-                // Temperate rainforest
-                if temp > 7.0 && temp < 22.0 && precip > 170.0 + 4.0 * temp {
-                    biome = Biome::TemperateSeasonalForest;
-                }
+        self.monthly_temperature = temperature;
+        self.monthly_precipitation = precipitation;
+    }
 
-                // Subtropical desert 50 cm at 22 C, 100 cm at 32 C
-                // 100 - 50 = 50, 32 - 22 = 10, slope = 50 / 10 = 5
-                // y-intercept = 50 - 22 * 5 = 50 - 110 = -60
-                if temp > 22.0 && precip < 5.0 * temp - 60.0 {
-                    biome = Biome::SubtropicalDesert;
-                }
+    /// Classify every tile from its climate, and land tiles also from their
+    /// height and the water around them, so this runs after `fill_rivers`.
+    pub fn generate_biome_map(&mut self, config: &AutoGenConfig) {
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                let temp = self.temperature_map[x][y];
+                let precip = self.precip_map[x][y];
 
-                // Tropical seasonal forest/savanna 230 cm at 22 C, 280 cm at 32 C
-                // 280 - 230 = 50, 32 - 22 = 10, slope = 50 / 10 = 5
-                // y-intercept = 280 - 22 * 5 = 280 - 110 = 170
-                if temp > 22.0 && precip > 5.0 * temp - 60.0 && precip < 5.0 * temp + 170.0 {
-                    biome = Biome::Savanna;
+                if self.features.get(&(x, y)) == Some(&Feature::Ocean) {
+                    self.biome_map[x][y] = config.biomes.classify(temp, precip);
+                    continue;
                 }
 
-                // Tropical rainforest
-                if temp > 22.0 && precip > 5.0 * temp + 170.0 {
-                    biome = Biome::TropicalRainforest;
+                let height = self.height_map[x][y];
+                let mut coastal = false;
+                let mut fresh_water = self.features.contains_key(&(x, y));
+                let mut relief: f64 = 0.0;
+                for n in self.height_map.get_neighbors(&(x, y)) {
+                    match self.features.get(&n) {
+                        Some(Feature::Ocean) => coastal = true,
+                        Some(_) => fresh_water = true,
+                        None => {}
+                    }
+                    relief = relief.max((height - self.height_map[n.0][n.1]).abs());
                 }
 
-                self.biome_map[x][y] = biome;
+                self.biome_map[x][y] = config.biomes.classify_tile(&TileConditions {
+                    temp,
+                    precip,
+                    elevation: height - self.lowland_level(),
+                    coastal,
+                    fresh_water,
+                    relief,
+                });
             }
         }
     }
 
-    pub fn river_path(
-        &mut self,
-        start: (usize, usize),
-        ocean_height: f64,
-        river_tile_limit: usize,
-    ) {
-        // Iterate until done
-        let mut lake = Lake::<X, Y>::new(self.height_map.clone());
-        let new_river_tiles = lake.fill(start, ocean_height, river_tile_limit);
-        for tile in new_river_tiles {
-            self.features.insert(tile, Feature::River);
+    /// Route water over the height map: fill depressions, find each tile's
+    /// downhill neighbor and accumulate precipitation runoff downstream, in
+    /// tiles of `full_rainfall` cm.
+    pub fn route_water(&mut self, ocean_height: f64, full_rainfall: f64) {
+        let (width, height) = (self.width(), self.height());
+        let mut outlets = DynGrid::new(width, height, false);
+        for x in 0..width {
+            for y in 0..height {
+                outlets[x][y] = self.height_map[x][y] < ocean_height;
+            }
+        }
+
+        self.filled_height_map = hydrology::fill_depressions(&self.height_map, &outlets);
+        self.flow_direction_map = hydrology::flow_directions(&self.filled_height_map, &outlets);
+
+        let mut runoff = DynGrid::new(width, height, 0.0);
+        for x in 0..width {
+            for y in 0..height {
+                runoff[x][y] = self.precip_map[x][y] / full_rainfall;
+            }
         }
+        self.flow_accumulation_map = hydrology::flow_accumulation(
+            &self.filled_height_map,
+            &self.flow_direction_map,
+            &runoff,
+        );
     }
 
     pub fn fill_rivers(&mut self, config: &AutoGenConfig) {
-        // Populate river sources
-        let mut rng = rand::thread_rng();
-        for x in 0..X {
-            for y in 0..Y {
-                let p = self.precip_map[x][y];
-
-                let mut any_sources = false;
-                let neighbors = self.height_map.get_neighbors(&(x, y));
-
-                for n in neighbors {
-                    if let Some(feature) = self.features.get(&n) {
-                        if *feature == Feature::RiverSource {
-                            any_sources = true;
-                            break;
-                        }
-                    }
+        self.route_water(self.sea_level, config.units.max_precipitation);
+
+        self.fill_lakes(config);
+
+        // Rivers run wherever enough water has collected
+        let mut rivers = vec![];
+        for x in 0..self.width() {
+            for y in 0..self.height() {
+                if self.height_map[x][y] >= self.sea_level
+                    && self.lake_map[x][y].is_none()
+                    && self.flow_accumulation_map[x][y] >= config.river_threshold
+                {
+                    rivers.push((x, y));
                 }
+            }
+        }
 
-                // If no neighboring sources, create one with some probability proportional to
-                // the precipitation at this tile.
-                if !any_sources {
-                    if rng.gen::<f64>() > 1.0 - 0.1 * p {
-                        // If above some height, x% chance for tile to be a
-                        // source.  Later add in contraint keeping sources
-                        // away from each other I'd think.
-                        self.features.insert((x, y), Feature::RiverSource);
+        // Any river tile not fed by another river tile or a lake is a source
+        let mut fed = DynGrid::new(self.width(), self.height(), false);
+        let lake_tiles = self.lakes.iter().flat_map(|l| l.tiles.iter());
+        for tile in rivers.iter().chain(lake_tiles) {
+            if let Some(r) = self.flow_direction_map[tile.0][tile.1] {
+                fed[r.0][r.1] = true;
+            }
+        }
+        for tile in rivers {
+            let feature = if fed[tile.0][tile.1] {
+                Feature::River
+            } else {
+                Feature::RiverSource
+            };
+            self.features.insert(tile, feature);
+        }
+
+        // Overflowing lakes continue downhill as rivers
+        for lake in &self.lakes {
+            let mut next = lake.outflow;
+            while let Some(tile) = next {
+                match self.features.get(&tile) {
+                    Some(Feature::Ocean) | Some(Feature::River) => break,
+                    Some(Feature::Lake) => {}
+                    _ => {
+                        self.features.insert(tile, Feature::River);
                     }
                 }
+                next = self.flow_direction_map[tile.0][tile.1];
+            }
+        }
+
+        self.river_network = RiverNetwork::build(
+            &self.features,
+            &self.flow_direction_map,
+            &self.flow_accumulation_map,
+            &self.lake_map,
+        );
+    }
+
+    /// Flood depressions deep enough to hold lakes.
+    ///
+    /// Lakes whose inflow can't keep up with evaporation off their surface
+    /// don't overflow: they keep the water they collect, which is taken back
+    /// out of the flow further downstream.  Expects `route_water` to have run.
+    pub fn fill_lakes(&mut self, config: &AutoGenConfig) {
+        let (mut lakes, lake_map) = hydrology::find_lakes(
+            &self.height_map,
+            &self.filled_height_map,
+            &self.flow_direction_map,
+            config.lake_min_depth,
+        );
+
+        // Upstream lakes sit higher, so settle those first
+        let mut order: Vec<usize> = (0..lakes.len()).collect();
+        order.sort_by(|a, b| lakes[*b].water_level.total_cmp(&lakes[*a].water_level));
+        for i in order {
+            let lake = &mut lakes[i];
+            let inflow = lake
+                .tiles
+                .iter()
+                .map(|t| self.flow_accumulation_map[t.0][t.1])
+                .fold(0.0, f64::max);
+            if inflow >= lake.area() as f64 * config.lake_evaporation {
+                continue;
+            }
+
+            let mut next = lake.outflow.take();
+            while let Some(tile) = next {
+                self.flow_accumulation_map[tile.0][tile.1] -= inflow;
+                next = self.flow_direction_map[tile.0][tile.1];
+            }
+            for tile in &lake.tiles {
+                self.flow_direction_map[tile.0][tile.1] = None;
             }
         }
 
-        // Path the rivers
-        let feature_copy = self.features.clone();
-        for (k, v) in feature_copy.iter() {
-            if *v == Feature::RiverSource {
-                self.river_path(*k, config.ocean_height, config.river_tile_limit);
+        for lake in &lakes {
+            for tile in &lake.tiles {
+                self.features.insert(*tile, Feature::Lake);
             }
         }
+
+        self.lakes = lakes;
+        self.lake_map = lake_map;
+    }
+
+    /// Label every land tile with the drainage basin it belongs to.
+    pub fn label_watersheds(&mut self) {
+        let mut ocean = DynGrid::new(self.width(), self.height(), false);
+        for (tile, feature) in &self.features {
+            if *feature == Feature::Ocean {
+                ocean[tile.0][tile.1] = true;
+            }
+        }
+
+        self.watersheds = Watersheds::build(
+            &ocean,
+            &self.filled_height_map,
+            &self.flow_direction_map,
+            &self.precip_map,
+            &self.lakes,
+            &self.lake_map,
+            &self.river_network,
+        );
     }
 
     pub fn autogen(&mut self, config: &AutoGenConfig) {
+        self.seed = self.world_seed(config);
+        // The stages overwrite their own maps, but features build up
+        self.features.clear();
+
         self.generate_height_map(config);
 
-        self.generate_precipitation_map(config);
+        self.erode(config);
+
+        self.generate_coast_distance_map();
+
+        self.populate_ocean(self.sea_level);
+
+        self.generate_seafloor_map(config);
+
+        self.generate_current_map(config);
 
         self.generate_temperature_map(config);
 
-        self.generate_biome_map();
+        self.generate_precipitation_map(config);
 
-        self.populate_ocean(config.ocean_height);
+        self.generate_seasons(config);
 
         self.fill_rivers(config);
+
+        self.generate_biome_map(config);
+
+        self.label_watersheds();
     }
 }
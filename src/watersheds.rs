@@ -0,0 +1,137 @@
+//! Drainage basins: which outlet every land tile's water ends up at.
+
+use crate::hydrology::Lake;
+use crate::rivers::RiverNetwork;
+use crate::terrain::DynGrid;
+use std::collections::HashMap;
+
+/// Where a drainage basin's water leaves the land.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outlet {
+    /// Ocean tile the basin empties into.
+    Ocean((usize, usize)),
+    /// Land tile on the map edge the water runs off from.
+    MapEdge((usize, usize)),
+    /// Endorheic lake, index into `Landmass::lakes`.
+    Lake(usize),
+    /// Land tile with nowhere lower to drain.
+    Sink((usize, usize)),
+}
+
+impl Outlet {
+    pub fn is_endorheic(&self) -> bool {
+        matches!(self, Outlet::Lake(_) | Outlet::Sink(_))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Basin {
+    pub outlet: Outlet,
+    /// Land area in tiles.
+    pub area: usize,
+    /// Mean annual rainfall over the basin in cm.
+    pub mean_precipitation: f64,
+    /// Index into `RiverNetwork::segments` of the largest river leaving the
+    /// basin, see `RiverNetwork::main_stem` for its full course.
+    pub main_river: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Watersheds {
+    pub basins: Vec<Basin>,
+    /// Index into `basins` for every land tile, `None` for ocean.
+    pub basin_map: DynGrid<Option<usize>>,
+}
+
+impl Watersheds {
+    /// Label every land tile with the basin it drains to, following
+    /// `flow_direction_map` down to the ocean, the map edge or a closed lake.
+    pub fn build(
+        ocean: &DynGrid<bool>,
+        filled_height_map: &DynGrid<f64>,
+        flow_direction_map: &DynGrid<Option<(usize, usize)>>,
+        precip_map: &DynGrid<f64>,
+        lakes: &[Lake],
+        lake_map: &DynGrid<Option<usize>>,
+        river_network: &RiverNetwork,
+    ) -> Watersheds {
+        let (width, height) = (ocean.width(), ocean.height());
+        let mut basin_map = DynGrid::new(width, height, None);
+        let mut basins: Vec<Basin> = vec![];
+        let mut by_outlet: HashMap<Outlet, usize> = HashMap::new();
+        let mut precip_total = vec![];
+
+        // Water only ever flows to lower tiles, so going bottom up every
+        // tile's receiver is labelled before the tile itself
+        let mut order: Vec<(usize, usize)> = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|t| !ocean[t.0][t.1])
+            .collect();
+        order.sort_unstable_by(|a, b| {
+            filled_height_map[a.0][a.1]
+                .total_cmp(&filled_height_map[b.0][b.1])
+                .then_with(|| a.cmp(b))
+        });
+
+        for (x, y) in order {
+            let basin = match flow_direction_map[x][y] {
+                Some(r) if !ocean[r.0][r.1] => basin_map[r.0][r.1],
+                receiver => {
+                    let outlet = match (receiver, lake_map[x][y]) {
+                        (Some(r), _) => Outlet::Ocean(r),
+                        (None, Some(lake)) if lakes[lake].outflow.is_none() => Outlet::Lake(lake),
+                        _ if x == 0 || y == 0 || x + 1 == width || y + 1 == height => {
+                            Outlet::MapEdge((x, y))
+                        }
+                        _ => Outlet::Sink((x, y)),
+                    };
+                    let index = *by_outlet.entry(outlet).or_insert_with(|| {
+                        basins.push(Basin {
+                            outlet,
+                            area: 0,
+                            mean_precipitation: 0.0,
+                            main_river: None,
+                        });
+                        precip_total.push(0.0);
+                        basins.len() - 1
+                    });
+                    Some(index)
+                }
+            };
+
+            if let Some(b) = basin {
+                basin_map[x][y] = Some(b);
+                basins[b].area += 1;
+                precip_total[b] += precip_map[x][y];
+            }
+        }
+
+        for (basin, total) in basins.iter_mut().zip(precip_total) {
+            basin.mean_precipitation = total / basin.area as f64;
+        }
+
+        // A basin's main river is the biggest one reaching its outlet
+        for mouth in river_network.mouths() {
+            let end = river_network.segments[mouth].end();
+            if let Some(b) = basin_map[end.0][end.1] {
+                let basin = &mut basins[b];
+                let bigger = match basin.main_river {
+                    Some(m) => {
+                        river_network.segments[mouth].discharge
+                            > river_network.segments[m].discharge
+                    }
+                    None => true,
+                };
+                if bigger {
+                    basin.main_river = Some(mouth);
+                }
+            }
+        }
+
+        Watersheds { basins, basin_map }
+    }
+
+    pub fn basin_at(&self, x: usize, y: usize) -> Option<&Basin> {
+        self.basin_map[x][y].map(|b| &self.basins[b])
+    }
+}
@@ -0,0 +1,66 @@
+use rustpcg::bathymetry::classify_seafloor;
+use rustpcg::{AutoGenConfig, Bathymetry, DynGrid, Landmass, Seafloor, TectonicPlates};
+
+/// Open ocean at `depth` below sea level 0, with land along the west edge.
+fn ocean_floor(depth: f64) -> (DynGrid<f64>, DynGrid<bool>) {
+    let mut height = DynGrid::new(24, 16, -depth);
+    let mut ocean = DynGrid::new(24, 16, true);
+    for y in 0..16 {
+        height[0][y] = 0.5;
+        ocean[0][y] = false;
+    }
+    (height, ocean)
+}
+
+#[test]
+fn gash_in_abyssal_plain_is_trench() {
+    let (mut height, ocean) = ocean_floor(0.3);
+    height[16][8] = -0.6;
+    let seafloor = classify_seafloor(&height, &ocean, 0.0, &Bathymetry::default());
+    assert_eq!(seafloor[16][8], Some(Seafloor::Trench));
+    assert_eq!(seafloor[12][8], Some(Seafloor::AbyssalPlain));
+    assert_eq!(seafloor[0][8], None);
+}
+
+#[test]
+fn shallow_hollow_is_not_trench() {
+    // Much deeper than the shelf around it, but nowhere near abyssal depth
+    let (mut height, ocean) = ocean_floor(0.05);
+    height[16][8] = -0.15;
+    let seafloor = classify_seafloor(&height, &ocean, 0.0, &Bathymetry::default());
+    assert_eq!(seafloor[16][8], Some(Seafloor::Slope));
+    assert_eq!(seafloor[3][8], Some(Seafloor::Shelf));
+}
+
+#[test]
+fn trenches_deeper_than_abyssal_plain() {
+    let config = AutoGenConfig {
+        plates: Some(TectonicPlates::default()),
+        ocean_height: 0.0,
+        seed: Some(1),
+        ..AutoGenConfig::default()
+    };
+    let mut land = Landmass::new(96, 48);
+    land.autogen(&config);
+
+    let mean_depth = |class: Seafloor| {
+        let mut depths = vec![];
+        for x in 0..land.width() {
+            for y in 0..land.height() {
+                if land.seafloor_map[x][y] == Some(class) {
+                    depths.push(land.sea_level - land.height_map[x][y]);
+                }
+            }
+        }
+        assert!(!depths.is_empty(), "no {:?} on the map", class);
+        depths.iter().sum::<f64>() / depths.len() as f64
+    };
+    let trench = mean_depth(Seafloor::Trench);
+    let plain = mean_depth(Seafloor::AbyssalPlain);
+    assert!(
+        trench > plain,
+        "trenches {} deep, plain {} deep",
+        trench,
+        plain
+    );
+}
@@ -0,0 +1,292 @@
+use rustpcg::{
+    AutoGenConfig, Biome, BiomeTable, Feature, Format, Landmass, Preset, TileConditions,
+    WorldConfig,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Low, flat, inland tile with the given climate.
+fn lowland(temp: f64, precip: f64) -> TileConditions {
+    TileConditions {
+        temp,
+        precip,
+        elevation: 0.2,
+        coastal: false,
+        fresh_water: false,
+        relief: 0.0,
+    }
+}
+
+/// Classify a grid of climates spanning everything the generator produces,
+/// on every kind of terrain.
+fn reachable(table: &BiomeTable) -> HashSet<Biome> {
+    let terrains = [
+        |t: &mut TileConditions| t.elevation = 0.7,
+        |t: &mut TileConditions| t.elevation = 0.5,
+        |t: &mut TileConditions| {
+            t.coastal = true;
+            t.elevation = 0.01;
+        },
+        |t: &mut TileConditions| t.fresh_water = true,
+        |_: &mut TileConditions| {},
+    ];
+    let mut found = HashSet::new();
+    for t in -20..=40 {
+        for p in 0..=450 {
+            for terrain in terrains.iter() {
+                let mut tile = lowland(t as f64, p as f64);
+                terrain(&mut tile);
+                found.insert(table.classify_tile(&tile));
+            }
+        }
+    }
+    found
+}
+
+#[test]
+fn every_biome_is_reachable() {
+    let found = reachable(&BiomeTable::whittaker());
+    for biome in Biome::ALL.iter() {
+        assert!(found.contains(biome), "{:?} is never produced", biome);
+    }
+}
+
+#[test]
+fn whittaker_spot_checks() {
+    let table = BiomeTable::whittaker();
+    assert_eq!(table.classify(-8.0, 100.0), Biome::IceSheet);
+    assert_eq!(table.classify(-3.0, 100.0), Biome::Tundra);
+    assert_eq!(table.classify(3.0, 100.0), Biome::BorealForest);
+    assert_eq!(table.classify(3.0, 35.0), Biome::Steppe);
+    assert_eq!(table.classify(3.0, 15.0), Biome::ColdDesert);
+    assert_eq!(table.classify(15.0, 300.0), Biome::TemperateRainforest);
+    assert_eq!(table.classify(15.0, 150.0), Biome::TemperateSeasonalForest);
+    assert_eq!(table.classify(15.0, 60.0), Biome::Shrubland);
+    assert_eq!(table.classify(27.0, 30.0), Biome::SubtropicalDesert);
+    assert_eq!(table.classify(27.0, 200.0), Biome::Savanna);
+    assert_eq!(table.classify(27.0, 350.0), Biome::TropicalRainforest);
+}
+
+#[test]
+fn landform_spot_checks() {
+    let table = BiomeTable::whittaker();
+    let tile = |f: fn(&mut TileConditions)| {
+        let mut tile = lowland(27.0, 200.0);
+        f(&mut tile);
+        table.classify_tile(&tile)
+    };
+    assert_eq!(tile(|_| {}), Biome::Savanna);
+    assert_eq!(tile(|t| t.elevation = 0.9), Biome::Alpine);
+    assert_eq!(tile(|t| t.elevation = 0.45), Biome::Montane);
+    assert_eq!(
+        tile(|t| {
+            t.coastal = true;
+            t.elevation = 0.02;
+        }),
+        Biome::Mangrove
+    );
+    assert_eq!(
+        tile(|t| {
+            t.coastal = true;
+            t.elevation = 0.02;
+            t.precip = 60.0;
+        }),
+        Biome::Beach
+    );
+    // High coast is a cliff, not a beach
+    assert_eq!(tile(|t| t.coastal = true), Biome::Savanna);
+    assert_eq!(tile(|t| t.fresh_water = true), Biome::Wetland);
+    assert_eq!(
+        tile(|t| {
+            t.fresh_water = true;
+            t.relief = 0.1;
+        }),
+        Biome::Savanna
+    );
+    // Frozen river banks aren't wetland, unless the table says so
+    let frozen = |t: &mut TileConditions| {
+        t.fresh_water = true;
+        t.temp = -2.0;
+    };
+    assert_eq!(tile(frozen), Biome::Tundra);
+    let mut cold_table = BiomeTable::whittaker();
+    cold_table.landforms.wetland_min_temp = -4.0;
+    let mut cold_bank = lowland(27.0, 200.0);
+    frozen(&mut cold_bank);
+    assert_eq!(cold_table.classify_tile(&cold_bank), Biome::Wetland);
+    // Ice covers mountains too
+    assert_eq!(
+        tile(|t| {
+            t.elevation = 0.9;
+            t.temp = -10.0;
+        }),
+        Biome::IceSheet
+    );
+}
+
+#[test]
+fn generated_climates_reach_every_rule() {
+    let config = AutoGenConfig::default();
+    let table = &config.biomes;
+    let mut found = HashSet::new();
+    for seed in 0..4 {
+        let mut land = Landmass::new(128, 64);
+        land.autogen(&AutoGenConfig {
+            seed: Some(seed),
+            ..config.clone()
+        });
+        for x in 0..land.width() {
+            for y in 0..land.height() {
+                if land.features.get(&(x, y)) != Some(&Feature::Ocean) {
+                    let (temp, precip) = (land.temperature_map[x][y], land.precip_map[x][y]);
+                    found.insert(table.classify(temp, precip));
+                }
+            }
+        }
+    }
+    for rule in table.rules.iter() {
+        assert!(
+            found.contains(&rule.biome),
+            "{:?} is never produced",
+            rule.biome
+        );
+    }
+}
+
+#[test]
+fn default_world_has_a_plausible_climate() {
+    let world = WorldConfig::default();
+    let config = AutoGenConfig {
+        seed: Some(7),
+        ..world.autogen
+    };
+    let mut land = Landmass::new(world.width, world.height);
+    land.autogen(&config);
+
+    let mut counts = HashMap::new();
+    for x in 0..land.width() {
+        for y in 0..land.height() {
+            let (temp, precip) = (land.temperature_map[x][y], land.precip_map[x][y]);
+            *counts
+                .entry(config.biomes.classify(temp, precip))
+                .or_insert(0) += 1;
+        }
+    }
+    let tiles = (world.width * world.height) as f64;
+    let share = |biome| counts.get(&biome).copied().unwrap_or(0) as f64 / tiles;
+    assert!(counts.len() >= 8, "only {:?}", counts);
+    assert!(share(Biome::IceSheet) < 0.15, "{:?}", counts);
+    assert!(
+        counts.values().all(|&n| (n as f64) < 0.5 * tiles),
+        "{:?}",
+        counts
+    );
+}
+
+#[test]
+fn presets_are_not_all_mountains() {
+    let world = WorldConfig::default();
+    for preset in Preset::ALL.iter() {
+        let config = AutoGenConfig {
+            seed: Some(7),
+            ..preset.config()
+        };
+        let mut land = Landmass::new(world.width, world.height);
+        land.autogen(&config);
+
+        let mut counts = HashMap::new();
+        for x in 0..land.width() {
+            for y in 0..land.height() {
+                *counts.entry(land.biome_map[x][y]).or_insert(0) += 1;
+            }
+        }
+        let tiles = (world.width * world.height) as f64;
+        let count = |biome| counts.get(&biome).copied().unwrap_or(0);
+        let mountains = (count(Biome::Alpine) + count(Biome::Montane)) as f64;
+        assert!(mountains < 0.15 * tiles, "{}: {:?}", preset.name(), counts);
+        assert!(counts.len() >= 8, "{}: {:?}", preset.name(), counts);
+    }
+}
+
+#[test]
+fn tree_lines_follow_sea_level() {
+    let config = AutoGenConfig {
+        ocean_height: 0.2,
+        seed: Some(5),
+        ..AutoGenConfig::default()
+    };
+    let land_forms = &config.biomes.landforms;
+    let mut land = Landmass::new(96, 48);
+    land.autogen(&config);
+
+    let mut high_lowland = 0;
+    for x in 0..land.width() {
+        for y in 0..land.height() {
+            if land.features.get(&(x, y)) == Some(&Feature::Ocean) {
+                continue;
+            }
+            let elevation = land.height_map[x][y] - land.sea_level;
+            let mountain = matches!(land.biome_map[x][y], Biome::Alpine | Biome::Montane);
+            assert_eq!(
+                mountain,
+                elevation >= land_forms.montane_height && land.biome_map[x][y] != Biome::IceSheet,
+                "({}, {}) at {:.2} above the sea is {:?}",
+                x,
+                y,
+                elevation,
+                land.biome_map[x][y]
+            );
+            high_lowland += (land.height_map[x][y] >= land_forms.montane_height
+                && elevation < land_forms.montane_height) as usize;
+        }
+    }
+    // Tiles above the tree line in absolute height but not above the sea
+    assert!(high_lowland > 0);
+}
+
+#[test]
+fn table_loads_from_config() {
+    let config = WorldConfig::from_str(
+        r#"
+        [autogen.biomes]
+        fallback = "ColdDesert"
+
+        [[autogen.biomes.rules]]
+        biome = "TemperateRainforest"
+        min_temp = 10.0
+        min_precip = { base = 100.0, per_degree = 2.0 }
+        "#,
+        Format::Toml,
+    )
+    .unwrap();
+
+    let table = &config.autogen.biomes;
+    assert_eq!(table.classify(20.0, 150.0), Biome::TemperateRainforest);
+    assert_eq!(table.classify(20.0, 130.0), Biome::ColdDesert);
+    assert_eq!(table.classify(5.0, 400.0), Biome::ColdDesert);
+}
+
+#[test]
+fn default_table_round_trips() {
+    let config = WorldConfig::default();
+    for format in [Format::Toml, Format::Json].iter() {
+        let text = config.to_string(*format).unwrap();
+        assert_eq!(WorldConfig::from_str(&text, *format).unwrap(), config);
+    }
+}
+
+#[test]
+fn empty_rule_is_rejected() {
+    let result = WorldConfig::from_str(
+        r#"
+        [autogen.biomes]
+        fallback = "Tundra"
+
+        [[autogen.biomes.rules]]
+        biome = "Savanna"
+        min_temp = 30.0
+        max_temp = 20.0
+        "#,
+        Format::Toml,
+    );
+    assert!(result.is_err());
+}
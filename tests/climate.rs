@@ -0,0 +1,241 @@
+use rustpcg::climate::{prevailing_wind, wind_precipitation};
+use rustpcg::{
+    AutoGenConfig, ClimateUnits, Continentality, DynGrid, Landmass, MoistureModel, NoiseNode,
+    Planet,
+};
+
+fn generate(config: &AutoGenConfig) -> Landmass {
+    let mut land = Landmass::new(48, 36);
+    land.autogen(config);
+    land
+}
+
+#[test]
+fn continentality_shifts_by_latitude() {
+    let continentality = Continentality::default();
+    for offset in [0.0, -0.3] {
+        // Without temperature noise, and with the planet mild enough that
+        // nothing saturates
+        let mut config = AutoGenConfig {
+            temperature_noise: Some(NoiseNode::Constant { value: 0.0 }),
+            temperature_offset: offset,
+            land_fraction: Some(0.4),
+            seed: Some(3),
+            ..AutoGenConfig::default()
+        };
+        config.planet.temperature_scale = 0.5;
+        let maritime = generate(&config);
+        config.continentality = Some(continentality.clone());
+        let continental = generate(&config);
+
+        let degrees_per_unit = (config.units.max_temperature - config.units.min_temperature) / 2.0;
+        for x in 0..maritime.width() {
+            for y in 0..maritime.height() {
+                let lat = config.planet.latitude(y, maritime.height());
+                let departure =
+                    config.planet.base_temperature(lat) - config.planet.mean_temperature();
+                let c = continentality.at(maritime.coast_distance_map[x][y]);
+                let expected = degrees_per_unit
+                    * continentality.temperature_contrast
+                    * (2.0 * c - 1.0)
+                    * departure;
+                let shift = continental.temperature_map[x][y] - maritime.temperature_map[x][y];
+                assert!(
+                    (shift - expected).abs() < 1e-9,
+                    "({}, {}) shifted {} instead of {}",
+                    x,
+                    y,
+                    shift,
+                    expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rainfall_drops_in_the_cold() {
+    let units = ClimateUnits::default();
+    let warm = units.rainfall_cm(1.0, units.max_temperature);
+    let cool = units.rainfall_cm(1.0, units.max_temperature - 10.0);
+    assert_eq!(warm, units.max_precipitation);
+    // Clausius–Clapeyron, about 7% less per °C
+    assert!((cool / warm - 1.07f64.powi(-10)).abs() < 1e-12);
+    assert!(cool / warm > 0.5 && cool / warm < 0.51);
+
+    // Half way along the noise gives half the rain
+    assert_eq!(units.rainfall_cm(0.0, units.max_temperature), warm / 2.0);
+    // and no drying at all leaves the temperature out of it
+    let wet = ClimateUnits {
+        cold_drying: 0.0,
+        ..ClimateUnits::default()
+    };
+    assert_eq!(wet.rainfall_cm(1.0, -20.0), wet.max_precipitation);
+}
+
+#[test]
+fn mid_latitude_coast_milder_than_interior() {
+    // Sea along the west edge of a band centered on 45 degrees north
+    let mut land = Landmass::new(60, 11);
+    for x in 0..10 {
+        for y in 0..11 {
+            land.height_map[x][y] = -0.5;
+        }
+    }
+    land.sea_level = 0.0;
+    land.generate_coast_distance_map();
+
+    let mut config = AutoGenConfig {
+        temperature_noise: Some(NoiseNode::Constant { value: 0.0 }),
+        continentality: Some(Continentality::default()),
+        ..AutoGenConfig::default()
+    };
+    config.planet.north_latitude = 50.0;
+    config.planet.south_latitude = 40.0;
+    assert_eq!(config.planet.latitude(5, 11), 45.0);
+    land.generate_temperature_map(&config);
+    let (coast, interior) = (land.temperature_map[10][5], land.temperature_map[55][5]);
+
+    config.continentality = None;
+    land.generate_temperature_map(&config);
+    let uniform = land.temperature_map[55][5];
+
+    assert!(
+        coast > uniform && uniform > interior,
+        "coast {}, interior {}, {} without continentality",
+        coast,
+        interior,
+        uniform
+    );
+    assert!(coast - interior > 1.5);
+}
+
+/// A band of the westerlies: sea along the west edge, then land at
+/// `land(x)`.  Returns the wind driven rain down the middle row.
+fn westerly_rain(land: impl Fn(f64) -> f64) -> Vec<f64> {
+    let (width, depth) = (60, 21);
+    let mut height = DynGrid::new(width, depth, -0.5);
+    for x in 20..width {
+        for y in 0..depth {
+            height[x][y] = land(x as f64);
+        }
+    }
+    let planet = Planet {
+        north_latitude: 50.0,
+        south_latitude: 40.0,
+        ..Planet::default()
+    };
+    // Air blowing in over the map edges is dry, so all the rain comes
+    // from the sea
+    let params = MoistureModel {
+        boundary_moisture: 0.0,
+        ..MoistureModel::default()
+    };
+    let rain = wind_precipitation(&height, 0.0, &planet, &params);
+    (0..width).map(|x| rain[x][depth / 2]).collect()
+}
+
+#[test]
+fn rain_shadow_behind_a_ridge() {
+    // A ridge across the wind
+    let rain = westerly_rain(|x| 0.05 + 0.6 * (-((x - 40.0) / 3.0).powi(2)).exp());
+    let flat = westerly_rain(|_| 0.05);
+
+    let windward = rain[35..40].iter().cloned().fold(f64::MIN, f64::max);
+    let lee = rain[42..50].iter().cloned().fold(f64::MAX, f64::min);
+    assert!(
+        windward > lee + 0.5,
+        "{} on the windward side, {} in the lee",
+        windward,
+        lee
+    );
+    // Wetter than the plain on the way up, drier beyond
+    assert!(windward > flat[38]);
+    assert!(lee < flat[45]);
+}
+
+#[test]
+fn rain_drops_inland() {
+    let rain = westerly_rain(|_| 0.05);
+    // Past the rise of the coast itself
+    for x in 24..59 {
+        assert!(
+            rain[x + 1] <= rain[x],
+            "{} tiles inland {} but {} the tile before",
+            x - 19,
+            rain[x + 1],
+            rain[x]
+        );
+    }
+    assert!(rain[24] - rain[50] > 0.4);
+}
+
+#[test]
+fn offset_equator() {
+    // 30N to 60S, so the equator runs a third of the way down
+    let planet = Planet {
+        north_latitude: 30.0,
+        south_latitude: -60.0,
+        ..Planet::default()
+    };
+    let rows = 90;
+    assert_eq!(planet.latitude(0, rows), 29.5);
+    assert_eq!(planet.latitude(rows - 1, rows), -59.5);
+
+    let base: Vec<f64> = (0..rows)
+        .map(|y| planet.base_temperature(planet.latitude(y, rows)))
+        .collect();
+    let warmest = (0..rows)
+        .max_by(|a, b| base[*a].total_cmp(&base[*b]))
+        .unwrap();
+    assert!(warmest == 29 || warmest == 30, "warmest row {}", warmest);
+    // The southern edge is twice as far from the equator, and colder
+    assert!(base[rows - 1] < base[0]);
+}
+
+#[test]
+fn southern_hemisphere_only() {
+    let planet = Planet {
+        north_latitude: 0.0,
+        south_latitude: -90.0,
+        ..Planet::default()
+    };
+    let rows = 18;
+    for y in 0..rows {
+        let lat = planet.latitude(y, rows);
+        assert!(lat < 0.0 && lat > -90.0);
+        if y > 0 {
+            // Colder towards the south pole at the bottom of the map
+            let north = planet.base_temperature(planet.latitude(y - 1, rows));
+            assert!(planet.base_temperature(lat) < north);
+        }
+    }
+    // Southern westerlies drift towards the pole, down the map
+    let wind = prevailing_wind(planet.latitude(rows / 2, rows));
+    assert!(wind.0 > 0.0 && wind.1 > 0.0);
+}
+
+#[test]
+fn temperature_scale_stretches_about_the_mean() {
+    let earth = Planet::default();
+    let even = Planet {
+        temperature_scale: 0.5,
+        ..Planet::default()
+    };
+    let extreme = Planet {
+        temperature_scale: 2.0,
+        ..Planet::default()
+    };
+    assert_eq!(earth.base_temperature(0.0), 0.8);
+    assert_eq!(earth.base_temperature(90.0), -0.5);
+
+    let range = |p: &Planet| p.base_temperature(0.0) - p.base_temperature(90.0);
+    assert!((range(&even) - 0.5 * range(&earth)).abs() < 1e-12);
+    assert!((range(&extreme) - 2.0 * range(&earth)).abs() < 1e-12);
+    for planet in [&even, &extreme].iter() {
+        assert_eq!(planet.mean_temperature(), earth.mean_temperature());
+        // The latitude of the mean temperature doesn't move
+        let mid = (std::f64::consts::FRAC_PI_2 - 1.0).to_degrees();
+        assert!((planet.base_temperature(mid) - earth.mean_temperature()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,131 @@
+use rustpcg::{AutoGenConfig, ConfigError, Format, Preset, ThermalErosion, WorldConfig};
+use std::fs;
+use std::path::PathBuf;
+
+/// Path in the temp directory unique to this test run.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rustpcg-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn negative_scale_is_rejected() {
+    let config = AutoGenConfig {
+        x_scale: -200.0,
+        ..AutoGenConfig::default()
+    };
+    let err = config.validate().unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::NonPositive {
+            field: "x_scale",
+            ..
+        }
+    ));
+    assert_eq!(err.to_string(), "x_scale must be positive, got -200");
+}
+
+#[test]
+fn zero_frequency_is_rejected() {
+    let result = WorldConfig::from_str("[autogen]\nlandmass_frequency = 0.0\n", Format::Toml);
+    match result {
+        Err(ConfigError::NonPositive { field, value }) => {
+            assert_eq!((field, value), ("landmass_frequency", 0.0))
+        }
+        other => panic!("expected NonPositive, got {:?}", other),
+    }
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let config = WorldConfig {
+        width: 64,
+        height: 32,
+        autogen: AutoGenConfig {
+            seed: Some(42),
+            land_fraction: Some(0.3),
+            ..Preset::Islands.config()
+        },
+    };
+    for name in ["round-trip.toml", "round-trip.json"].iter() {
+        let path = temp_path(name);
+        config.save(&path).unwrap();
+        let loaded = WorldConfig::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), config, "{}", name);
+    }
+}
+
+#[test]
+fn unknown_extension_is_rejected() {
+    let path = temp_path("world.yaml");
+    match WorldConfig::default().save(&path) {
+        Err(ConfigError::UnknownFormat(p)) => assert_eq!(p, path),
+        other => panic!("expected UnknownFormat, got {:?}", other),
+    }
+    assert!(!path.exists());
+    assert!(matches!(
+        WorldConfig::load(&path),
+        Err(ConfigError::UnknownFormat(_))
+    ));
+    assert!(matches!(
+        WorldConfig::load(temp_path("world")),
+        Err(ConfigError::UnknownFormat(_))
+    ));
+}
+
+#[test]
+fn ocean_height_checked_before_masks() {
+    let config = AutoGenConfig {
+        ocean_height: -3.0,
+        ocean_border: 4,
+        ..AutoGenConfig::default()
+    };
+    match config.validate() {
+        Err(ConfigError::OceanHeightOutOfRange(value)) => assert_eq!(value, -3.0),
+        other => panic!("expected OceanHeightOutOfRange, got {:?}", other),
+    }
+}
+
+#[test]
+fn talus_angle_may_be_zero() {
+    let mut config = AutoGenConfig {
+        thermal_erosion: Some(ThermalErosion {
+            talus_angle: 0.0,
+            ..ThermalErosion::default()
+        }),
+        ..AutoGenConfig::default()
+    };
+    assert!(config.validate().is_ok());
+
+    config.thermal_erosion.as_mut().unwrap().talus_angle = -0.1;
+    let err = config.validate().unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::Negative {
+            field: "talus_angle",
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "talus_angle must not be negative, got -0.1"
+    );
+}
+
+#[test]
+fn negative_lake_evaporation_is_rejected() {
+    let mut config = AutoGenConfig {
+        lake_evaporation: 0.0,
+        ..AutoGenConfig::default()
+    };
+    assert!(config.validate().is_ok());
+
+    config.lake_evaporation = -1.0;
+    assert!(matches!(
+        config.validate(),
+        Err(ConfigError::Negative {
+            field: "lake_evaporation",
+            ..
+        })
+    ));
+}
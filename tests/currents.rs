@@ -0,0 +1,54 @@
+use rustpcg::currents::current_anomaly;
+use rustpcg::{ClimateUnits, DynGrid, OceanCurrents, Planet};
+
+const WIDTH: usize = 60;
+const DEPTH: usize = 30;
+
+/// Shift in °C the currents bring to a subtropical ocean from 40 degrees
+/// north to 10, between a continent along the west edge (x < 10) and one
+/// along the east edge (x >= 50).
+fn basin_anomaly() -> DynGrid<f64> {
+    let mut ocean = DynGrid::new(WIDTH, DEPTH, false);
+    for x in 10..50 {
+        for y in 0..DEPTH {
+            ocean[x][y] = true;
+        }
+    }
+    let planet = Planet {
+        north_latitude: 40.0,
+        south_latitude: 10.0,
+        ..Planet::default()
+    };
+    let units = ClimateUnits::default();
+    current_anomaly(&ocean, &planet, &units, &OceanCurrents::default())
+}
+
+#[test]
+fn western_boundary_current_warms_its_coast() {
+    let anomaly = basin_anomaly();
+    for y in DEPTH / 3..DEPTH * 2 / 3 {
+        assert!(anomaly[10][y] > 1.0, "sea at row {} {}", y, anomaly[10][y]);
+        assert!(anomaly[9][y] > 0.0, "coast at row {} {}", y, anomaly[9][y]);
+        // fading inland
+        assert!(anomaly[9][y] > anomaly[5][y] && anomaly[5][y] > 0.0);
+        // Warm water flows north, up the map, into ever colder latitudes
+        assert!(anomaly[10][y - 1] > anomaly[10][y]);
+    }
+}
+
+#[test]
+fn eastern_boundary_current_cools_its_coast() {
+    let anomaly = basin_anomaly();
+    for y in DEPTH / 3..DEPTH * 2 / 3 {
+        assert!(anomaly[49][y] < -1.0, "sea at row {} {}", y, anomaly[49][y]);
+        assert!(
+            anomaly[50][y] < 0.0,
+            "coast at row {} {}",
+            y,
+            anomaly[50][y]
+        );
+        assert!(anomaly[50][y] < anomaly[54][y] && anomaly[54][y] < 0.0);
+        // Cold water flows south, towards the equator
+        assert!(anomaly[49][y + 1] < anomaly[49][y]);
+    }
+}
@@ -0,0 +1,154 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rustpcg::erosion::{hydraulic_erosion, thermal_erosion};
+use rustpcg::{DynGrid, HydraulicErosion, ThermalErosion};
+
+fn total(grid: &DynGrid<f64>) -> f64 {
+    grid.to_vecs().iter().flatten().sum()
+}
+
+/// Height map from `f(x, y)` on a `size` x `size` grid.
+fn fixture(size: usize, f: impl Fn(f64, f64) -> f64) -> DynGrid<f64> {
+    let mut height = DynGrid::new(size, size, 0.0);
+    for x in 0..size {
+        for y in 0..size {
+            height[x][y] = f(x as f64, y as f64);
+        }
+    }
+    height
+}
+
+fn run_hydraulic(height: &mut DynGrid<f64>, params: &HydraulicErosion) -> DynGrid<f64> {
+    let mut sediment = DynGrid::new(height.width(), height.height(), 0.0);
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+    hydraulic_erosion(height, &mut sediment, params, -1.0, &mut rng);
+    sediment
+}
+
+#[test]
+fn hydraulic_conserves_mass() {
+    // A bowl, so no droplet runs off the map with its load
+    let mut height = fixture(32, |x, y| {
+        let (dx, dy) = (x - 15.5, y - 15.5);
+        0.001 * (dx * dx + dy * dy)
+    });
+    let before = height.clone();
+    let params = HydraulicErosion {
+        iterations: 100,
+        ..HydraulicErosion::default()
+    };
+    let sediment = run_hydraulic(&mut height, &params);
+
+    let (mut eroded, mut deposited) = (0.0, 0.0);
+    for x in 0..32 {
+        for y in 0..32 {
+            let change = height[x][y] - before[x][y];
+            if change < 0.0 {
+                eroded -= change;
+            } else {
+                deposited += change;
+            }
+        }
+    }
+    assert!(eroded > 0.01, "only {} eroded", eroded);
+    assert!(
+        (eroded - deposited).abs() < 1e-9,
+        "{} eroded but {} deposited",
+        eroded,
+        deposited
+    );
+    assert!(total(&sediment) > 0.0);
+}
+
+#[test]
+fn hydraulic_carves_a_ramp() {
+    let mut height = fixture(32, |x, _| 1.0 - x / 31.0);
+    let before = height.clone();
+    let params = HydraulicErosion {
+        iterations: 2_000,
+        ..HydraulicErosion::default()
+    };
+    run_hydraulic(&mut height, &params);
+
+    let mut deepest: f64 = 0.0;
+    for x in 0..32 {
+        for y in 0..32 {
+            deepest = deepest.min(height[x][y] - before[x][y]);
+        }
+    }
+    assert!(deepest < -0.01, "deepest cut only {}", deepest);
+    // Droplets wash material off the low end of the ramp
+    assert!(total(&height) < total(&before));
+}
+
+#[test]
+fn hydraulic_without_droplets_changes_nothing() {
+    let mut height = fixture(16, |x, y| (x * 0.3).sin() + (y * 0.2).cos());
+    let before = height.clone();
+    let params = HydraulicErosion {
+        iterations: 0,
+        ..HydraulicErosion::default()
+    };
+    let sediment = run_hydraulic(&mut height, &params);
+    assert_eq!(height, before);
+    assert_eq!(total(&sediment), 0.0);
+}
+
+fn run_thermal(height: &mut DynGrid<f64>, params: &ThermalErosion) {
+    let mut sediment = DynGrid::new(height.width(), height.height(), 0.0);
+    thermal_erosion(height, &mut sediment, params);
+}
+
+/// Steepest slope between neighbors past `talus`, per unit distance.
+fn steepest_excess(height: &DynGrid<f64>, talus: f64) -> f64 {
+    let mut steepest: f64 = 0.0;
+    for x in 0..height.width() {
+        for y in 0..height.height() {
+            for n in height.get_neighbors(&(x, y)) {
+                let distance = if n.0 != x && n.1 != y {
+                    std::f64::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                let slope = (height[x][y] - height[n.0][n.1]) / distance;
+                steepest = steepest.max(slope - talus);
+            }
+        }
+    }
+    steepest
+}
+
+#[test]
+fn thermal_relaxes_a_spike() {
+    let mut height = fixture(15, |x, y| if x == 7.0 && y == 7.0 { 1.0 } else { 0.0 });
+    let before = total(&height);
+    let params = ThermalErosion {
+        iterations: 500,
+        talus_angle: 0.1,
+        rate: 0.5,
+    };
+    assert!(steepest_excess(&height, params.talus_angle) > 0.5);
+    run_thermal(&mut height, &params);
+
+    assert!(steepest_excess(&height, params.talus_angle) < 1e-3);
+    assert!(height[7][7] < 1.0);
+    assert!((total(&height) - before).abs() < 1e-9);
+}
+
+#[test]
+fn thermal_zero_talus_flattens() {
+    let mut height = fixture(9, |x, y| (x * 0.7).sin() * (y * 0.4).cos());
+    let before = total(&height);
+    let params = ThermalErosion {
+        iterations: 2_000,
+        talus_angle: 0.0,
+        rate: 0.5,
+    };
+    run_thermal(&mut height, &params);
+
+    let heights: Vec<f64> = height.to_vecs().into_iter().flatten().collect();
+    let low = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    assert!(high - low < 1e-3, "still {} from top to bottom", high - low);
+    assert!((total(&height) - before).abs() < 1e-9);
+}
@@ -0,0 +1,92 @@
+use rustpcg::{DynGrid, Grid};
+
+/// 3 x 2 grid holding `10 * x + y` at `[x][y]`.
+fn numbered() -> DynGrid<usize> {
+    let mut grid = DynGrid::new(3, 2, 0);
+    for x in 0..3 {
+        for y in 0..2 {
+            grid[x][y] = 10 * x + y;
+        }
+    }
+    grid
+}
+
+fn sorted(mut points: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    points.sort();
+    points
+}
+
+#[test]
+fn indexes_column_major() {
+    let grid = numbered();
+    assert_eq!((grid.width(), grid.height()), (3, 2));
+    // Each column is one x, running down y
+    assert_eq!(grid[0], [0, 1]);
+    assert_eq!(grid[2], [20, 21]);
+    assert_eq!(grid[1][1], 11);
+
+    let mut grid = grid;
+    grid[2][0] = 99;
+    assert_eq!(grid[2], [99, 21]);
+    assert_eq!(grid[1], [10, 11]);
+}
+
+#[test]
+#[should_panic(expected = "x index 3 out of bounds")]
+fn out_of_bounds_column_panics() {
+    let _ = numbered()[3][0];
+}
+
+#[test]
+fn neighbors_stay_on_the_grid() {
+    let grid = DynGrid::new(4, 3, 0.0);
+    // Corners
+    assert_eq!(
+        sorted(grid.get_neighbors(&(0, 0))),
+        vec![(0, 1), (1, 0), (1, 1)]
+    );
+    assert_eq!(
+        sorted(grid.get_neighbors(&(3, 2))),
+        vec![(2, 1), (2, 2), (3, 1)]
+    );
+    // Edges
+    assert_eq!(
+        sorted(grid.get_neighbors(&(0, 1))),
+        vec![(0, 0), (0, 2), (1, 0), (1, 1), (1, 2)]
+    );
+    assert_eq!(
+        sorted(grid.get_neighbors(&(2, 0))),
+        vec![(1, 0), (1, 1), (2, 1), (3, 0), (3, 1)]
+    );
+    // Interior
+    assert_eq!(grid.get_neighbors(&(1, 1)).len(), 8);
+}
+
+#[test]
+fn to_vecs_is_column_major() {
+    assert_eq!(
+        numbered().to_vecs(),
+        vec![vec![0, 1], vec![10, 11], vec![20, 21]]
+    );
+}
+
+#[test]
+fn converts_from_grid() {
+    let grid = Grid::new([[0, 1], [10, 11], [20, 21]]);
+    let dyn_grid = DynGrid::from(grid.clone());
+    assert_eq!(dyn_grid, numbered());
+    assert_eq!(
+        (dyn_grid.width(), dyn_grid.height()),
+        (grid.width(), grid.height())
+    );
+    assert_eq!(dyn_grid.to_vecs(), grid.to_vecs());
+    for x in 0..3 {
+        for y in 0..2 {
+            assert_eq!(dyn_grid[x][y], grid[x][y]);
+            assert_eq!(
+                sorted(dyn_grid.get_neighbors(&(x, y))),
+                sorted(grid.get_neighbors(&(x, y)))
+            );
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use rustpcg::{ConfigError, DynGrid, HeightmapFile};
+use std::fs;
+use std::path::PathBuf;
+
+/// Path in the temp directory unique to this test run.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rustpcg-{}-{}", std::process::id(), name))
+}
+
+/// The grid's values row by row, top row first.
+fn rows(grid: &DynGrid<f64>) -> Vec<Vec<f64>> {
+    (0..grid.height())
+        .map(|y| (0..grid.width()).map(|x| grid[x][y]).collect())
+        .collect()
+}
+
+fn load_grid(name: &str, text: &str, width: usize, height: usize) -> Result<Vec<Vec<f64>>, String> {
+    let path = temp_path(name);
+    fs::write(&path, text).unwrap();
+    let result = HeightmapFile::new(&path).load(width, height);
+    fs::remove_file(&path).unwrap();
+    match result {
+        Ok(grid) => Ok(rows(&grid)),
+        Err(ConfigError::Heightmap { reason, .. }) => Err(reason),
+        Err(e) => panic!("unexpected error {}", e),
+    }
+}
+
+#[test]
+fn ascii_grid() {
+    let text = "ncols 3\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 30\n\
+                0 5 10\n10 5 0\n";
+    let rows = load_grid("valid.asc", text, 3, 2).unwrap();
+    assert_eq!(rows, vec![vec![-1.0, 0.0, 1.0], vec![1.0, 0.0, -1.0]]);
+}
+
+#[test]
+fn ascii_grid_nodata() {
+    let text = "ncols 2\nnrows 2\nnodata_value -9999\n-9999 100\n200 300\n";
+    let rows = load_grid("nodata.asc", text, 2, 2).unwrap();
+    assert_eq!(rows, vec![vec![-1.0, -1.0], vec![0.0, 1.0]]);
+
+    let text = "ncols 2\nnrows 1\nNODATA_value -9999\n-9999 -9999\n";
+    let reason = load_grid("all-nodata.asc", text, 2, 2).unwrap_err();
+    assert_eq!(reason, "every cell is nodata");
+}
+
+#[test]
+fn truncated_ascii_grid() {
+    let text = "ncols 3\nnrows 2\n0 5 10\n10 5\n";
+    let reason = load_grid("truncated.asc", text, 3, 2).unwrap_err();
+    assert_eq!(reason, "expected 6 cells, got 5");
+
+    let text = "ncols 3\nnrows 2\n0 5 10\n10 5 0 7\n";
+    let reason = load_grid("long.asc", text, 3, 2).unwrap_err();
+    assert_eq!(reason, "expected 6 cells, got 7");
+}
+
+#[test]
+fn non_finite_ascii_grid() {
+    for (text, reason) in &[
+        (
+            "ncols 2\nnrows 1\n1 NaN\n",
+            "bad cell 'NaN': expected a finite number",
+        ),
+        (
+            "ncols 2\nnrows 1\n1 inf\n",
+            "bad cell 'inf': expected a finite number",
+        ),
+        (
+            "ncols 2\nnrows 1\n-infinity 1\n",
+            "bad cell '-infinity': expected a finite number",
+        ),
+        (
+            "ncols 2\nnrows 1\nnodata_value nan\n1 2\n",
+            "bad nodata_value 'nan': expected a finite number",
+        ),
+        (
+            "ncols 2\nnrows 1\n1 x2\n",
+            "bad cell 'x2': invalid float literal",
+        ),
+    ] {
+        assert_eq!(
+            load_grid("non-finite.asc", text, 2, 1).unwrap_err(),
+            *reason
+        );
+    }
+}
+
+#[test]
+fn bad_ascii_grid_dimensions() {
+    for (header, reason) in &[
+        (
+            "ncols 1e12\nnrows 1",
+            "bad ncols '1e12': expected a whole number above 0",
+        ),
+        (
+            "ncols 2.5\nnrows 1",
+            "bad ncols '2.5': expected a whole number above 0",
+        ),
+        (
+            "ncols 2\nnrows 0",
+            "bad nrows '0': expected a whole number above 0",
+        ),
+        (
+            "ncols 2\nnrows -1",
+            "bad nrows '-1': expected a whole number above 0",
+        ),
+        (
+            "ncols 100000\nnrows 100000",
+            "100000x100000 grid is over the 16777216 cell limit",
+        ),
+        (
+            "ncols 18446744073709551615\nnrows 2",
+            "18446744073709551615x2 grid is over the 16777216 cell limit",
+        ),
+    ] {
+        let text = format!("{}\n1 2\n", header);
+        assert_eq!(
+            load_grid("oversized.asc", &text, 2, 1).unwrap_err(),
+            *reason
+        );
+    }
+}
+
+#[test]
+fn validate_reports_bad_grid() {
+    let path = temp_path("validate.asc");
+    fs::write(&path, "ncols 1e12\nnrows 1e12\n0\n").unwrap();
+    let result = HeightmapFile::new(&path).validate();
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(ConfigError::Heightmap { .. })));
+}
+
+#[cfg(feature = "image")]
+mod png {
+    use super::{rows, temp_path};
+    use image::{ImageBuffer, Luma};
+    use rustpcg::HeightmapFile;
+    use std::fs;
+
+    #[test]
+    fn eight_bit() {
+        let path = temp_path("eight.png");
+        ImageBuffer::from_fn(3, 1, |x, _| Luma([[0u8, 51, 255][x as usize]]))
+            .save(&path)
+            .unwrap();
+        let grid = HeightmapFile::new(&path).load(3, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rows(&grid), vec![vec![-1.0, -0.6, 1.0]]);
+    }
+
+    #[test]
+    fn sixteen_bit() {
+        let path = temp_path("sixteen.png");
+        ImageBuffer::from_fn(3, 1, |x, _| Luma([[0u16, 13107, 65535][x as usize]]))
+            .save(&path)
+            .unwrap();
+        let grid = HeightmapFile::new(&path).load(3, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rows(&grid), vec![vec![-1.0, -0.6, 1.0]]);
+    }
+
+    #[test]
+    fn custom_range() {
+        let path = temp_path("range.png");
+        ImageBuffer::from_fn(2, 1, |x, _| Luma([[100u8, 200][x as usize]]))
+            .save(&path)
+            .unwrap();
+        let file = HeightmapFile {
+            low: Some(100.0),
+            high: Some(200.0),
+            ..HeightmapFile::new(&path)
+        };
+        let grid = file.load(2, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rows(&grid), vec![vec![-1.0, 1.0]]);
+    }
+}
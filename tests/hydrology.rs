@@ -0,0 +1,134 @@
+use rustpcg::hydrology::{fill_depressions, find_lakes, flow_accumulation, flow_directions};
+use rustpcg::DynGrid;
+
+fn grid(width: usize, height: usize, f: impl Fn(usize, usize) -> f64) -> DynGrid<f64> {
+    let mut grid = DynGrid::new(width, height, 0.0);
+    for x in 0..width {
+        for y in 0..height {
+            grid[x][y] = f(x, y);
+        }
+    }
+    grid
+}
+
+/// 5x5 basin walled in at 2.0, with a pit at 0.1 in the middle whose rim
+/// is lowest (0.5) towards a gap in the west wall.
+fn pit() -> DynGrid<f64> {
+    grid(5, 5, |x, y| match (x, y) {
+        (0, 2) => 0.0,
+        (1, 2) => 0.5,
+        (2, 2) => 0.1,
+        (0, _) | (4, _) | (_, 0) | (_, 4) => 2.0,
+        _ => 1.0,
+    })
+}
+
+/// Plane rising to the east, one unit per tile.
+fn ramp(width: usize, height: usize) -> DynGrid<f64> {
+    grid(width, height, |x, _| x as f64)
+}
+
+#[test]
+fn pit_filled_to_spill_height() {
+    let height = pit();
+    let outlets = DynGrid::new(5, 5, false);
+    let filled = fill_depressions(&height, &outlets);
+
+    let raised = filled[2][2] - 0.5;
+    assert!(
+        raised > 0.0 && raised < 1e-6,
+        "pit filled to {}",
+        filled[2][2]
+    );
+    for x in 0..5 {
+        for y in 0..5 {
+            assert!(filled[x][y] >= height[x][y]);
+            if (x, y) != (2, 2) {
+                assert_eq!(filled[x][y], height[x][y], "({}, {}) raised", x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn filled_surface_drains() {
+    // Bumpy surface full of little pits
+    let height = grid(16, 12, |x, y| ((x * 7 + y * 13) % 11) as f64 / 11.0);
+    let outlets = DynGrid::new(16, 12, false);
+    let filled = fill_depressions(&height, &outlets);
+    let receivers = flow_directions(&filled, &outlets);
+
+    for x in 1..15 {
+        for y in 1..11 {
+            let r = receivers[x][y].expect("interior tile doesn't drain");
+            assert!(filled[r.0][r.1] < filled[x][y]);
+        }
+    }
+}
+
+#[test]
+fn directions_point_downhill() {
+    let height = ramp(6, 4);
+    let outlets = DynGrid::new(6, 4, false);
+    let receivers = flow_directions(&height, &outlets);
+
+    for y in 0..4 {
+        assert_eq!(receivers[0][y], None);
+        for x in 1..6 {
+            assert_eq!(receivers[x][y], Some((x - 1, y)));
+        }
+    }
+}
+
+#[test]
+fn outlets_have_no_direction() {
+    let height = ramp(6, 4);
+    let mut outlets = DynGrid::new(6, 4, false);
+    outlets[3][1] = true;
+    let receivers = flow_directions(&height, &outlets);
+    assert_eq!(receivers[3][1], None);
+    assert_eq!(receivers[4][1], Some((3, 1)));
+}
+
+#[test]
+fn accumulation_counts_upstream_cells() {
+    let height = ramp(6, 4);
+    let outlets = DynGrid::new(6, 4, false);
+    let receivers = flow_directions(&height, &outlets);
+    let runoff = DynGrid::new(6, 4, 1.0);
+    let accumulation = flow_accumulation(&height, &receivers, &runoff);
+
+    for x in 0..6 {
+        for y in 0..4 {
+            assert_eq!(accumulation[x][y], (6 - x) as f64);
+        }
+    }
+}
+
+#[test]
+fn pit_becomes_lake() {
+    let height = pit();
+    let outlets = DynGrid::new(5, 5, false);
+    let filled = fill_depressions(&height, &outlets);
+    let receivers = flow_directions(&filled, &outlets);
+    let (lakes, lake_map) = find_lakes(&height, &filled, &receivers, 0.01);
+
+    assert_eq!(lakes.len(), 1);
+    assert_eq!(lakes[0].tiles, vec![(2, 2)]);
+    assert_eq!(lakes[0].outflow, Some((1, 2)));
+    assert!((lakes[0].max_depth(&height) - 0.4).abs() < 1e-6);
+    assert_eq!(lake_map[2][2], Some(0));
+    assert_eq!(lake_map[1][2], None);
+}
+
+#[test]
+fn lake_without_outflow_is_endorheic() {
+    let height = pit();
+    let mut filled = height.clone();
+    filled[2][2] = 0.5;
+    let receivers = DynGrid::new(5, 5, None);
+    let (lakes, _) = find_lakes(&height, &filled, &receivers, 0.01);
+
+    assert_eq!(lakes.len(), 1);
+    assert_eq!(lakes[0].outflow, None);
+}
@@ -0,0 +1,102 @@
+use rustpcg::Koppen;
+
+/// Monthly means (°C) and totals (mm), January first, roughly those of real
+/// stations.
+struct Station {
+    temperature: [f64; 12],
+    precipitation: [f64; 12],
+}
+
+impl Station {
+    fn classify(&self) -> Koppen {
+        Koppen::classify(&self.temperature, &self.precipitation, true)
+    }
+}
+
+/// Singapore
+const SINGAPORE: Station = Station {
+    temperature: [
+        26.5, 27.1, 27.5, 28.0, 28.3, 28.3, 27.9, 27.9, 27.6, 27.6, 26.9, 26.4,
+    ],
+    precipitation: [
+        234.0, 113.0, 170.0, 154.0, 171.0, 132.0, 158.0, 176.0, 163.0, 194.0, 256.0, 287.0,
+    ],
+};
+
+/// Cairo
+const CAIRO: Station = Station {
+    temperature: [
+        14.0, 15.0, 18.0, 22.0, 25.0, 27.0, 28.0, 28.0, 26.0, 23.0, 19.0, 15.0,
+    ],
+    precipitation: [5.0, 4.0, 4.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 3.0, 6.0],
+};
+
+/// London
+const LONDON: Station = Station {
+    temperature: [
+        5.0, 5.0, 7.0, 9.0, 13.0, 16.0, 18.0, 18.0, 15.0, 12.0, 8.0, 6.0,
+    ],
+    precipitation: [
+        55.0, 41.0, 42.0, 44.0, 49.0, 45.0, 45.0, 50.0, 49.0, 69.0, 59.0, 55.0,
+    ],
+};
+
+/// Fairbanks
+const FAIRBANKS: Station = Station {
+    temperature: [
+        -23.0, -19.0, -12.0, 0.0, 9.0, 15.0, 17.0, 14.0, 8.0, -3.0, -16.0, -21.0,
+    ],
+    precipitation: [
+        14.0, 11.0, 8.0, 10.0, 15.0, 35.0, 55.0, 45.0, 25.0, 20.0, 17.0, 15.0,
+    ],
+};
+
+/// Utqiaġvik (Barrow)
+const BARROW: Station = Station {
+    temperature: [
+        -26.0, -28.0, -26.0, -18.0, -7.0, 1.0, 4.0, 3.0, -1.0, -9.0, -18.0, -23.0,
+    ],
+    precipitation: [
+        4.0, 4.0, 3.0, 3.0, 3.0, 8.0, 25.0, 25.0, 15.0, 10.0, 5.0, 4.0,
+    ],
+};
+
+#[test]
+fn tropical_rainforest() {
+    assert_eq!(SINGAPORE.classify(), Koppen::Af);
+}
+
+#[test]
+fn hot_desert() {
+    assert_eq!(CAIRO.classify(), Koppen::BWh);
+}
+
+#[test]
+fn oceanic() {
+    assert_eq!(LONDON.classify(), Koppen::Cfb);
+}
+
+#[test]
+fn subarctic() {
+    assert_eq!(FAIRBANKS.classify(), Koppen::Dfc);
+}
+
+#[test]
+fn tundra() {
+    assert_eq!(BARROW.classify(), Koppen::ET);
+}
+
+#[test]
+fn southern_hemisphere_seasons() {
+    // Half a year out of step, south of the equator
+    let mut temperature = [0.0; 12];
+    let mut precipitation = [0.0; 12];
+    for m in 0..12 {
+        temperature[m] = FAIRBANKS.temperature[(m + 6) % 12];
+        precipitation[m] = FAIRBANKS.precipitation[(m + 6) % 12];
+    }
+    assert_eq!(
+        Koppen::classify(&temperature, &precipitation, false),
+        Koppen::Dfc
+    );
+}
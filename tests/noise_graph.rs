@@ -0,0 +1,123 @@
+use rustpcg::{ConfigError, Format, NoiseNode, WorldConfig};
+
+/// One graph rooted at each node type, as it would be written in a config.
+const GRAPHS: [&str; 12] = [
+    r#"type = "fbm"
+       frequency = 2.0
+       octaves = 4"#,
+    r#"type = "ridged_multi"
+       lacunarity = 2.5"#,
+    r#"type = "billow"
+       persistence = 0.3"#,
+    r#"type = "perlin"
+       frequency = 3.0"#,
+    r#"type = "worley"
+       output = "distance""#,
+    r#"type = "constant"
+       value = 0.25"#,
+    r#"type = "add"
+       sources = [{ type = "fbm" }, { type = "constant", value = 0.1 }]"#,
+    r#"type = "multiply"
+       sources = [{ type = "perlin" }, { type = "billow" }]"#,
+    r#"type = "select"
+       outside = { type = "constant", value = -0.5 }
+       inside = { type = "fbm" }
+       control = { type = "perlin" }
+       lower_bound = 0.0
+       upper_bound = 1.0
+       falloff = 0.1"#,
+    r#"type = "terrace"
+       source = { type = "fbm" }
+       control_points = [-1.0, 0.0, 0.5, 1.0]"#,
+    r#"type = "warp"
+       source = { type = "fbm" }
+       power = 0.5
+       roughness = 2"#,
+    r#"type = "scale_bias"
+       source = { type = "fbm" }
+       scale = 0.5
+       bias = 0.2"#,
+];
+
+fn load(graph: &str) -> Result<WorldConfig, ConfigError> {
+    WorldConfig::from_str(&format!("[autogen.height_noise]\n{}", graph), Format::Toml)
+}
+
+fn height_noise(graph: &str) -> NoiseNode {
+    load(graph)
+        .unwrap_or_else(|e| panic!("{} rejected: {}", graph, e))
+        .autogen
+        .height_noise
+        .unwrap()
+}
+
+#[test]
+fn every_node_type_builds_and_round_trips() {
+    for graph in GRAPHS.iter() {
+        let config = load(graph).unwrap_or_else(|e| panic!("{} rejected: {}", graph, e));
+        let node = config.autogen.height_noise.as_ref().unwrap();
+        assert!(node.validate().is_ok());
+
+        let noise = node.build(17);
+        for i in 0..16 {
+            let value = noise.get([i as f64 * 0.37, i as f64 * 0.11]);
+            assert!(value.is_finite(), "{} gave {}", graph, value);
+        }
+
+        for format in [Format::Toml, Format::Json].iter() {
+            let text = config.to_string(*format).unwrap();
+            assert_eq!(WorldConfig::from_str(&text, *format).unwrap(), config);
+        }
+    }
+}
+
+#[test]
+fn same_seed_same_noise() {
+    let node = height_noise(GRAPHS[6]);
+    let (a, b) = (node.build(3), node.build(3));
+    assert_eq!(a.get([0.3, 0.7]), b.get([0.3, 0.7]));
+}
+
+#[test]
+fn malformed_graphs_are_rejected() {
+    let malformed = [
+        // Unknown node type
+        r#"type = "voronoi""#,
+        // Missing the required value
+        r#"type = "constant""#,
+        r#"type = "add"
+           sources = []"#,
+        r#"type = "select"
+           outside = { type = "fbm" }
+           inside = { type = "fbm" }
+           control = { type = "perlin" }
+           lower_bound = 1.0
+           upper_bound = 0.0"#,
+        r#"type = "terrace"
+           source = { type = "fbm" }
+           control_points = [0.5, 0.5]"#,
+        r#"type = "fbm"
+           octaves = 0"#,
+        r#"type = "warp"
+           source = { type = "perlin", frequency = -1.0 }"#,
+    ];
+    for graph in malformed.iter() {
+        assert!(load(graph).is_err(), "{} accepted", graph);
+    }
+}
+
+#[test]
+fn octaves_are_capped() {
+    assert!(load("type = \"fbm\"\noctaves = 32").is_ok());
+    for graph in [
+        "type = \"billow\"\noctaves = 1000000",
+        "type = \"warp\"\nsource = { type = \"fbm\" }\nroughness = 33",
+    ]
+    .iter()
+    {
+        match load(graph) {
+            Err(ConfigError::OutOfRange { max, .. }) => assert_eq!(max, 32.0),
+            other => panic!("expected OutOfRange for {}, got {:?}", graph, other),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rustpcg::plates::{generate_plates, plate_relief};
+use rustpcg::{Crust, DynGrid, Plate, TectonicPlates};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 12;
+
+fn generate(seed: u64) -> (Vec<Plate>, DynGrid<usize>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    generate_plates(
+        WIDTH,
+        HEIGHT,
+        &TectonicPlates::default(),
+        seed as u32,
+        &mut rng,
+    )
+}
+
+/// Two continental plates meeting along the line between columns 15 and
+/// 16, the western one moving east at `speed` and the eastern one west.
+fn two_continents(speed: f64) -> DynGrid<f64> {
+    let plates = [
+        Plate {
+            center: (8.0, 6.0),
+            crust: Crust::Continental,
+            velocity: (speed, 0.0),
+        },
+        Plate {
+            center: (24.0, 6.0),
+            crust: Crust::Continental,
+            velocity: (-speed, 0.0),
+        },
+    ];
+    let mut plate_map = DynGrid::new(WIDTH, HEIGHT, 0);
+    for x in WIDTH / 2..WIDTH {
+        for y in 0..HEIGHT {
+            plate_map[x][y] = 1;
+        }
+    }
+    plate_relief(&plates, &plate_map, &TectonicPlates::default())
+}
+
+#[test]
+fn plates_are_deterministic_per_seed() {
+    let (plates, plate_map) = generate(9);
+    let (again, again_map) = generate(9);
+    assert_eq!(plates, again);
+    assert_eq!(plate_map, again_map);
+
+    let (other, other_map) = generate(10);
+    assert!(plates != other || plate_map != other_map);
+}
+
+#[test]
+fn converging_continents_raise_mountains() {
+    let relief = two_continents(1.0);
+    let interior = TectonicPlates::default().continental_height;
+    for y in 0..HEIGHT {
+        assert!((relief[0][y] - interior).abs() < 1e-9);
+        for x in [15, 16].iter() {
+            assert!(
+                relief[*x][y] > interior + 0.5,
+                "boundary at ({}, {}) only {}",
+                x,
+                y,
+                relief[*x][y]
+            );
+        }
+    }
+}
+
+#[test]
+fn diverging_continents_open_a_rift() {
+    let relief = two_continents(-1.0);
+    let interior = TectonicPlates::default().continental_height;
+    for y in 0..HEIGHT {
+        assert!((relief[0][y] - interior).abs() < 1e-9);
+        for x in [15, 16].iter() {
+            assert!(
+                relief[*x][y] < interior - 0.1,
+                "boundary at ({}, {}) still {}",
+                x,
+                y,
+                relief[*x][y]
+            );
+        }
+    }
+}
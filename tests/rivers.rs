@@ -0,0 +1,95 @@
+use rustpcg::{DynGrid, Feature, RiverNetwork, RiverSegment};
+use std::collections::HashMap;
+
+/// Two headwater streams joining into a stem that a third joins further
+/// down, each given as its tiles in flow order:
+///
+/// ```text
+/// a . . . b
+/// . a . b
+/// . . c
+/// . . c . . d
+/// . . c . d
+/// . . e d
+/// . . e
+/// . . e
+/// ```
+fn confluence() -> RiverNetwork {
+    let streams: [&[(usize, usize)]; 3] = [
+        &[
+            (2, 1),
+            (3, 2),
+            (4, 3),
+            (4, 4),
+            (4, 5),
+            (4, 6),
+            (4, 7),
+            (4, 8),
+        ],
+        &[(6, 1), (5, 2), (4, 3)],
+        &[(7, 4), (6, 5), (5, 6), (4, 6)],
+    ];
+
+    let mut features = HashMap::new();
+    let mut directions = DynGrid::new(9, 10, None);
+    let mut accumulation = DynGrid::new(9, 10, 0.0);
+    for stream in &streams {
+        features.insert(stream[0], Feature::RiverSource);
+        for pair in stream.windows(2) {
+            features.entry(pair[1]).or_insert(Feature::River);
+            directions[pair[0].0][pair[0].1] = Some(pair[1]);
+        }
+        for (i, t) in stream.iter().enumerate() {
+            accumulation[t.0][t.1] += (i + 1) as f64;
+        }
+    }
+    let lakes = DynGrid::new(9, 10, None);
+    RiverNetwork::build(&features, &directions, &accumulation, &lakes)
+}
+
+fn segment(network: &RiverNetwork, tile: (usize, usize)) -> &RiverSegment {
+    network.segment_at(tile.0, tile.1).expect("no river there")
+}
+
+#[test]
+fn segments_split_at_confluences() {
+    let network = confluence();
+    assert_eq!(network.segments.len(), 5);
+    assert_eq!(
+        segment(&network, (4, 4)).tiles,
+        vec![(4, 3), (4, 4), (4, 5)]
+    );
+    assert_eq!(
+        segment(&network, (4, 7)).tiles,
+        vec![(4, 6), (4, 7), (4, 8)]
+    );
+
+    let mut confluences = network.confluences();
+    confluences.sort_unstable();
+    assert_eq!(confluences, vec![(4, 3), (4, 6)]);
+    let mouths = network.mouths();
+    assert_eq!(mouths.len(), 1);
+    assert_eq!(network.segments[mouths[0]].end(), (4, 8));
+}
+
+#[test]
+fn strahler_order() {
+    let network = confluence();
+    for headwater in &[(2, 1), (6, 1), (7, 4)] {
+        assert_eq!(segment(&network, *headwater).strahler, 1);
+    }
+    // 1 + 1 -> 2
+    assert_eq!(segment(&network, (4, 4)).strahler, 2);
+    // 2 + 1 -> 2
+    assert_eq!(segment(&network, (4, 7)).strahler, 2);
+}
+
+#[test]
+fn shreve_order() {
+    let network = confluence();
+    for headwater in &[(2, 1), (6, 1), (7, 4)] {
+        assert_eq!(segment(&network, *headwater).shreve, 1);
+    }
+    assert_eq!(segment(&network, (4, 4)).shreve, 2);
+    assert_eq!(segment(&network, (4, 7)).shreve, 3);
+}
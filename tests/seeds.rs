@@ -0,0 +1,96 @@
+use rustpcg::{AutoGenConfig, Landmass, TectonicPlates};
+
+fn generate(config: &AutoGenConfig) -> Landmass {
+    let mut land = Landmass::new(48, 24);
+    land.autogen(config);
+    land
+}
+
+/// Seeded config with precipitation that doesn't depend on temperature, so
+/// each climate layer only sees its own stage's noise.
+fn seeded() -> AutoGenConfig {
+    let mut config = AutoGenConfig {
+        seed: Some(1234),
+        plates: Some(TectonicPlates::default()),
+        ..AutoGenConfig::default()
+    };
+    config.units.cold_drying = 0.0;
+    config
+}
+
+#[test]
+fn same_seed_same_world() {
+    let config = seeded();
+    let (a, b) = (generate(&config), generate(&config));
+    assert_eq!(a.height_map, b.height_map);
+    assert_eq!(a.plate_map, b.plate_map);
+    assert_eq!(a.temperature_map, b.temperature_map);
+    assert_eq!(a.precip_map, b.precip_map);
+    assert_eq!(a.biome_map, b.biome_map);
+}
+
+#[test]
+fn seed_overrides_landmass_seed() {
+    let config = seeded();
+    let mut a = Landmass::new(48, 24);
+    let mut b = Landmass::new(48, 24);
+    a.seed = 1;
+    b.seed = 2;
+    a.autogen(&config);
+    b.autogen(&config);
+    assert_eq!(a.seed, 1234);
+    assert_eq!(a.height_map, b.height_map);
+}
+
+#[test]
+fn different_seeds_differ() {
+    let a = generate(&seeded());
+    let b = generate(&AutoGenConfig {
+        seed: Some(4321),
+        ..seeded()
+    });
+    assert_ne!(a.height_map, b.height_map);
+    assert_ne!(a.precip_map, b.precip_map);
+}
+
+#[test]
+fn precipitation_stage_leaves_others_alone() {
+    let a = generate(&seeded());
+    let b = generate(&AutoGenConfig {
+        precip_frequency: 3.0,
+        precip_offset: 0.2,
+        ..seeded()
+    });
+    assert_ne!(a.precip_map, b.precip_map);
+    assert_eq!(a.height_map, b.height_map);
+    assert_eq!(a.plate_map, b.plate_map);
+    assert_eq!(a.temperature_map, b.temperature_map);
+}
+
+#[test]
+fn temperature_stage_leaves_others_alone() {
+    let a = generate(&seeded());
+    let b = generate(&AutoGenConfig {
+        temperature_frequency: 5.0,
+        ..seeded()
+    });
+    assert_ne!(a.temperature_map, b.temperature_map);
+    assert_eq!(a.height_map, b.height_map);
+    assert_eq!(a.plate_map, b.plate_map);
+    assert_eq!(a.precip_map, b.precip_map);
+}
+
+#[test]
+fn regenerating_starts_afresh() {
+    let islands = AutoGenConfig {
+        ocean_height: 0.25,
+        ..seeded()
+    };
+    let mut land = generate(&islands);
+    land.autogen(&seeded());
+    let fresh = generate(&seeded());
+    assert_eq!(land.features, fresh.features);
+    assert_eq!(land.lakes, fresh.lakes);
+    assert_eq!(land.river_network, fresh.river_network);
+    assert_eq!(land.biome_map, fresh.biome_map);
+}
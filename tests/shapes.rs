@@ -0,0 +1,96 @@
+use rustpcg::{AutoGenConfig, Feature, Landmass, ShapeMask, ThermalErosion};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 40;
+
+fn generate(config: &AutoGenConfig) -> Landmass {
+    let mut land = Landmass::new(WIDTH, HEIGHT);
+    land.autogen(config);
+    land
+}
+
+fn is_ocean(land: &Landmass, x: usize, y: usize) -> bool {
+    land.features.get(&(x, y)) == Some(&Feature::Ocean)
+}
+
+#[test]
+fn border_always_ocean() {
+    let border = 10;
+    for seed in 0..4 {
+        let land = generate(&AutoGenConfig {
+            land_fraction: Some(0.9),
+            ocean_border: border,
+            seed: Some(seed),
+            ..AutoGenConfig::default()
+        });
+        assert!(land.sea_level > -1.0);
+
+        let mut inside = (0, 0);
+        for x in 0..WIDTH {
+            for y in 0..HEIGHT {
+                let edge = x.min(y).min(WIDTH - 1 - x).min(HEIGHT - 1 - y);
+                if edge < border {
+                    assert!(is_ocean(&land, x, y), "({}, {}) above water", x, y);
+                } else {
+                    inside.0 += !is_ocean(&land, x, y) as usize;
+                    inside.1 += 1;
+                }
+            }
+        }
+        // The share of land is of the tiles the border leaves
+        let share = inside.0 as f64 / inside.1 as f64;
+        assert!(share > 0.8, "only {:.2} of the interior is land", share);
+    }
+}
+
+#[test]
+fn masked_out_tiles_always_ocean() {
+    let config = AutoGenConfig {
+        land_fraction: Some(1.0),
+        shape: Some(ShapeMask::Island {
+            radius: 0.4,
+            falloff: 0.2,
+        }),
+        seed: Some(7),
+        ..AutoGenConfig::default()
+    };
+    assert!(config.validate().is_ok());
+    let land = generate(&config);
+    for &(x, y) in &[
+        (0, 0),
+        (WIDTH - 1, 0),
+        (0, HEIGHT - 1),
+        (WIDTH - 1, HEIGHT - 1),
+    ] {
+        assert!(is_ocean(&land, x, y), "corner ({}, {}) above water", x, y);
+    }
+    assert!(!is_ocean(&land, WIDTH / 2, HEIGHT / 2));
+}
+
+#[test]
+fn eroded_border_stays_under_water() {
+    // Thermal erosion spills material from the coast onto the border
+    let border = 6;
+    let config = AutoGenConfig {
+        land_fraction: Some(1.0),
+        ocean_border: border,
+        thermal_erosion: Some(ThermalErosion {
+            talus_angle: 0.0,
+            ..ThermalErosion::default()
+        }),
+        seed: Some(3),
+        ..AutoGenConfig::default()
+    };
+    assert!(config.validate().is_ok());
+    let land = generate(&config);
+    for x in 0..WIDTH {
+        for y in 0..HEIGHT {
+            let edge = x.min(y).min(WIDTH - 1 - x).min(HEIGHT - 1 - y);
+            if edge < border {
+                assert!(is_ocean(&land, x, y), "({}, {}) above water", x, y);
+            } else {
+                assert!(!is_ocean(&land, x, y), "({}, {}) under water", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use rustpcg::hydrology::{fill_depressions, flow_directions};
+use rustpcg::{DynGrid, Outlet, RiverNetwork, Watersheds};
+
+const WIDTH: usize = 12;
+const HEIGHT: usize = 7;
+
+/// Land sloping down to two single-tile bays in the middle of the west and
+/// east edges, so the divide runs between columns 5 and 6.
+fn two_basins() -> Watersheds {
+    let bays = [(0, 3), (WIDTH - 1, 3)];
+    let mut ocean = DynGrid::new(WIDTH, HEIGHT, false);
+    let mut height = DynGrid::new(WIDTH, HEIGHT, 0.0);
+    for x in 0..WIDTH {
+        for y in 0..HEIGHT {
+            ocean[x][y] = bays.contains(&(x, y));
+            height[x][y] = bays
+                .iter()
+                .map(|b| (x as f64 - b.0 as f64).hypot(y as f64 - b.1 as f64))
+                .fold(f64::MAX, f64::min);
+        }
+    }
+
+    let filled = fill_depressions(&height, &ocean);
+    let directions = flow_directions(&filled, &ocean);
+    let precip = DynGrid::new(WIDTH, HEIGHT, 100.0);
+    let lake_map = DynGrid::new(WIDTH, HEIGHT, None);
+    Watersheds::build(
+        &ocean,
+        &filled,
+        &directions,
+        &precip,
+        &[],
+        &lake_map,
+        &RiverNetwork::default(),
+    )
+}
+
+#[test]
+fn every_land_tile_in_one_basin() {
+    let watersheds = two_basins();
+    assert_eq!(watersheds.basins.len(), 2);
+
+    let mut areas = vec![0; 2];
+    for x in 0..WIDTH {
+        for y in 0..HEIGHT {
+            match watersheds.basin_map[x][y] {
+                Some(b) => areas[b] += 1,
+                None => assert!(
+                    y == 3 && (x == 0 || x == WIDTH - 1),
+                    "({}, {}) unlabelled",
+                    x,
+                    y
+                ),
+            }
+        }
+    }
+    for (basin, area) in watersheds.basins.iter().zip(areas) {
+        assert_eq!(basin.area, area);
+        assert_eq!(basin.mean_precipitation, 100.0);
+    }
+    let total: usize = watersheds.basins.iter().map(|b| b.area).sum();
+    assert_eq!(total, WIDTH * HEIGHT - 2);
+}
+
+#[test]
+fn divide_between_the_bays() {
+    let watersheds = two_basins();
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            if watersheds.basin_map[x][y].is_none() {
+                continue;
+            }
+            let bay = if x < WIDTH / 2 {
+                (0, 3)
+            } else {
+                (WIDTH - 1, 3)
+            };
+            let basin = watersheds.basin_at(x, y).unwrap();
+            assert_eq!(basin.outlet, Outlet::Ocean(bay), "({}, {})", x, y);
+        }
+    }
+}